@@ -3,9 +3,13 @@
 
 pub mod inky_map;
 pub mod mono_map;
+pub mod palette_map;
 
 pub use inky_map::*;
 pub use mono_map::*;
+pub use palette_map::*;
+
+use image::Rgb;
 
 /// Supported color maps for spatial quantization of images
 #[derive(Debug, Copy, Clone)]
@@ -13,3 +17,24 @@ pub enum SupportedColorMaps {
     InkyFourColor(InkyFourColorMap),
     Mono(MonoColorMap),
 }
+
+/// Returns the index of the palette color nearest to the argument color by squared Euclidean distance in RGB.
+pub(crate) fn nearest_index(palette: impl IntoIterator<Item = Rgb<u8>>, color: &Rgb<u8>) -> usize {
+    let mut best_index = 0usize;
+    let mut best_distance = i32::MAX;
+
+    for (index, palette_color) in palette.into_iter().enumerate() {
+        // It would be sweet if image::Rgb<_> implemented ops::Sub, but alas
+        let dr = color[0] as i32 - palette_color[0] as i32;
+        let dg = color[1] as i32 - palette_color[1] as i32;
+        let db = color[2] as i32 - palette_color[2] as i32;
+        let distance = dr.pow(2) + dg.pow(2) + db.pow(2);
+
+        if distance < best_distance {
+            best_distance = distance;
+            best_index = index;
+        }
+    }
+
+    best_index
+}
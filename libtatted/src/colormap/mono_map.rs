@@ -4,6 +4,7 @@
 use image::{Rgb, imageops::ColorMap};
 use strum::{Display, EnumIter, IntoEnumIterator};
 
+use super::nearest_index;
 use crate::InkyError;
 
 #[derive(Copy, Clone, Debug, EnumIter, Display)]
@@ -43,25 +44,7 @@ impl ColorMap for MonoColorMap {
     type Color = Rgb<u8>;
 
     fn index_of(&self, color: &Self::Color) -> usize {
-        let mut best_index = 0usize;
-        let mut best_distance = i32::MAX;
-
-        for (index, palette_item) in MonoColorPalette::iter().enumerate() {
-            let palette_color = Rgb::from(palette_item);
-
-            // It would be sweet if image::Rgb<_> implemented ops::Sub, but alas
-            let dr = color[0] as i32 - palette_color[0] as i32;
-            let dg = color[1] as i32 - palette_color[1] as i32;
-            let db = color[2] as i32 - palette_color[2] as i32;
-            let distance = dr.pow(2) + dg.pow(2) + db.pow(2);
-
-            if distance < best_distance {
-                best_distance = distance;
-                best_index = index;
-            }
-        }
-
-        best_index
+        nearest_index(MonoColorPalette::iter().map(Rgb::from), color)
     }
 
     fn has_lookup(&self) -> bool {
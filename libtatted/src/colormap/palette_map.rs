@@ -0,0 +1,148 @@
+//! A colormap over an arbitrary palette of colors, for panels whose palettes don't warrant a dedicated enum
+//! such as the 7-color UC8159 and Spectra 6 displays.
+
+use image::{Rgb, imageops::ColorMap};
+use strum::IntoEnumIterator;
+
+use super::nearest_index;
+use crate::{
+    InkyError, InkyFourColorMap, InkyFourColorPalette, InkyResult, MonoColorMap, MonoColorPalette,
+};
+
+/// Maximum number of colors in a palette, indices must fit in the single byte per pixel of an index image.
+const MAX_PALETTE_LEN: usize = 256;
+
+/// A [`ColorMap`] which quantizes to the nearest of an arbitrary list of colors, an index image pixel's value
+/// is the index of its color in the list.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PaletteColorMap {
+    palette: Vec<Rgb<u8>>,
+}
+
+impl PaletteColorMap {
+    /// Construct a new [`PaletteColorMap`], the index of each color in the argument palette is the value its
+    /// pixels take on in the index image.
+    ///
+    /// Returns an error if the palette is empty or has more than 256 colors.
+    pub fn new(palette: impl Into<Vec<Rgb<u8>>>) -> InkyResult<Self> {
+        let palette = palette.into();
+
+        if palette.is_empty() || palette.len() > MAX_PALETTE_LEN {
+            return Err(InkyError::InvalidPalette { len: palette.len() });
+        }
+
+        Ok(Self { palette })
+    }
+
+    /// The colors in the palette, in index order
+    pub fn palette(&self) -> &[Rgb<u8>] {
+        &self.palette
+    }
+
+    /// The number of bits required to represent each pixel when bit-packing an index image using this palette,
+    /// rounded up to a width that packs evenly into bytes.
+    pub fn bits_per_pixel(&self) -> u8 {
+        match self.palette.len() {
+            0..=2 => 1,
+            3..=4 => 2,
+            5..=16 => 4,
+            _ => 8,
+        }
+    }
+}
+
+impl From<InkyFourColorMap> for PaletteColorMap {
+    fn from(_: InkyFourColorMap) -> Self {
+        Self {
+            palette: InkyFourColorPalette::iter().map(Rgb::from).collect(),
+        }
+    }
+}
+
+impl From<MonoColorMap> for PaletteColorMap {
+    fn from(_: MonoColorMap) -> Self {
+        Self {
+            palette: MonoColorPalette::iter().map(Rgb::from).collect(),
+        }
+    }
+}
+
+impl ColorMap for PaletteColorMap {
+    type Color = Rgb<u8>;
+
+    fn index_of(&self, color: &Self::Color) -> usize {
+        nearest_index(self.palette.iter().copied(), color)
+    }
+
+    fn has_lookup(&self) -> bool {
+        true
+    }
+
+    fn lookup(&self, index: usize) -> Option<Self::Color> {
+        self.palette.get(index).copied()
+    }
+
+    fn map_color(&self, color: &mut Self::Color) {
+        let nearest_color_index = self.index_of(color);
+        let nearest_color = self
+            .lookup(nearest_color_index)
+            .expect("it is a logic error to hit this index out of bounds");
+
+        *color = nearest_color
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray_palette(len: usize) -> Vec<Rgb<u8>> {
+        (0..len).map(|i| Rgb([i as u8; 3])).collect()
+    }
+
+    #[test]
+    fn new_rejects_bad_lengths() {
+        for len in [0, 257] {
+            let err = PaletteColorMap::new(gray_palette(len)).unwrap_err();
+
+            assert!(matches!(err, InkyError::InvalidPalette { len: found } if found == len));
+        }
+
+        assert!(PaletteColorMap::new(gray_palette(256)).is_ok());
+    }
+
+    #[test]
+    fn bits_per_pixel_boundaries() {
+        let cases = [
+            (1, 1),
+            (2, 1),
+            (3, 2),
+            (4, 2),
+            (5, 4),
+            (16, 4),
+            (17, 8),
+            (256, 8),
+        ];
+
+        for (len, bits) in cases {
+            let map = PaletteColorMap::new(gray_palette(len)).unwrap();
+
+            assert_eq!(map.bits_per_pixel(), bits, "palette of {len} colors");
+        }
+    }
+
+    #[test]
+    fn matches_inky_four_color_map() {
+        let map = PaletteColorMap::from(InkyFourColorMap);
+
+        for r in (0..=255).step_by(15) {
+            for g in (0..=255).step_by(15) {
+                for b in (0..=255).step_by(15) {
+                    let color = Rgb([r as u8, g as u8, b as u8]);
+
+                    assert_eq!(map.index_of(&color), InkyFourColorMap.index_of(&color));
+                }
+            }
+        }
+    }
+}
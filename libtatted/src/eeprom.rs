@@ -0,0 +1,348 @@
+//! Reading the identification EEPROM on Pimoroni Inky displays.
+//!
+//! Every Inky board carries a small I2C EEPROM at address 0x50 which describes the panel it is attached to,
+//! the layout matches the `<HHBBB22p` struct used by the Pimoroni python library:
+//!
+//! | bytes | field                                          |
+//! |-------|------------------------------------------------|
+//! | 0..2  | width in pixels, little-endian u16             |
+//! | 2..4  | height in pixels, little-endian u16            |
+//! | 4     | color                                          |
+//! | 5     | PCB variant                                    |
+//! | 6     | display variant, see [`DISPLAY_VARIANT_NAMES`] |
+//! | 7..29 | write timestamp as a length-prefixed string    |
+
+use crate::Resolution;
+use crate::peripherals::list_matching;
+use camino::Utf8PathBuf;
+use i2cdev::core::I2CDevice;
+use i2cdev::linux::LinuxI2CDevice;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use tabled::builder::Builder;
+use thiserror::Error;
+
+/// I2C address of the EEPROM on Inky boards.
+pub const EEPROM_ADDRESS: u16 = 0x50;
+
+/// Number of bytes of display information stored in the EEPROM.
+pub const EEPROM_LENGTH: usize = 29;
+
+/// Human-readable names for the display variant byte, indexed by its value. Gaps in the numbering are
+/// represented by `None`. Mirrors `DISPLAY_VARIANT` from the Pimoroni python library.
+pub const DISPLAY_VARIANT_NAMES: [Option<&str>; 26] = [
+    None,
+    Some("Red pHAT (High-Temp)"),
+    Some("Yellow wHAT"),
+    Some("Black wHAT"),
+    Some("Black pHAT"),
+    Some("Yellow pHAT"),
+    Some("Red wHAT"),
+    Some("Red wHAT (High-Temp)"),
+    Some("Red wHAT"),
+    None,
+    Some("Black pHAT (SSD1608)"),
+    Some("Red pHAT (SSD1608)"),
+    Some("Yellow pHAT (SSD1608)"),
+    None,
+    Some("7-Colour (UC8159)"),
+    Some("7-Colour 640x400 (UC8159)"),
+    Some("7-Colour 640x400 (UC8159)"),
+    Some("Black wHAT (SSD1683)"),
+    Some("Red wHAT (SSD1683)"),
+    Some("Yellow wHAT (SSD1683)"),
+    Some("7-Colour 800x480 (AC073TC1A)"),
+    Some("Spectra 6 13.3 1600 x 1200 (EL133UF1)"),
+    Some("Spectra 6 7.3 800 x 480 (E673)"),
+    Some("Red/Yellow pHAT (JD79661)"),
+    Some("Red/Yellow wHAT (JD79668)"),
+    Some("Spectra 6 4.0 400 x 600 (E640)"),
+];
+
+/// Offset of the length-prefixed write timestamp string.
+const WRITE_TIME_OFFSET: usize = 7;
+
+/// Display variant byte for the JD79668 wHAT.
+const JD79668_VARIANT: u8 = 24;
+
+/// Displays which this crate knows how to drive.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DisplaySpec {
+    Jd79668,
+}
+
+/// Display information parsed from an Inky EEPROM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EepromInfo {
+    pub width: u16,
+    pub height: u16,
+    pub color: u8,
+    pub pcb_variant: u8,
+    pub display_variant: u8,
+
+    /// When the EEPROM was programmed, a free-form string written by Pimoroni's tooling, e.g.
+    /// `2025-03-14 10:22:31.5`. `None` if the string is empty or its length prefix is out of range.
+    pub write_time: Option<String>,
+}
+
+impl EepromInfo {
+    /// The human-readable name of the display variant, if it's a known one.
+    pub fn variant_name(&self) -> Option<&'static str> {
+        DISPLAY_VARIANT_NAMES
+            .get(self.display_variant as usize)
+            .copied()
+            .flatten()
+    }
+
+    /// The [`DisplaySpec`] for this display, if it's one we support.
+    pub fn display_spec(&self) -> Option<DisplaySpec> {
+        match self.display_variant {
+            JD79668_VARIANT => Some(DisplaySpec::Jd79668),
+            _ => None,
+        }
+    }
+
+    /// The resolution of the display as stored in the EEPROM.
+    pub fn resolution(&self) -> Resolution {
+        Resolution::new(self.width.into(), self.height.into())
+    }
+}
+
+/// Reasons the contents of an Inky EEPROM can't be interpreted as display information.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Error)]
+pub enum EepromParseError {
+    /// The EEPROM has never been written
+    #[error("the display EEPROM is blank, it has never been programmed")]
+    Blank,
+
+    /// The EEPROM contents are too short or describe a display with no pixels
+    #[error("the display EEPROM contents could not be parsed")]
+    Invalid,
+}
+
+/// The outcome of attempting to read an Inky EEPROM on a particular I2C bus.
+#[derive(Debug)]
+pub enum I2cProbeStatus {
+    /// The EEPROM was read and contained valid display information
+    Detected(EepromInfo),
+
+    /// The EEPROM responded but its contents could not be interpreted as display information
+    Unparseable(EepromParseError),
+
+    /// Nothing responded at the EEPROM address, or the bus could not be opened
+    NoResponse(io::Error),
+}
+
+/// Read and parse the Inky EEPROM on the I2C bus at the argument path, e.g. `/dev/i2c-1`.
+pub fn read_eeprom<P: AsRef<Path>>(path: P) -> I2cProbeStatus {
+    let buf = match read_eeprom_raw(path) {
+        Ok(buf) => buf,
+        Err(e) => return I2cProbeStatus::NoResponse(e),
+    };
+
+    match parse_eeprom(&buf) {
+        Ok(info) => I2cProbeStatus::Detected(info),
+        Err(e) => I2cProbeStatus::Unparseable(e),
+    }
+}
+
+/// Read the raw, unparsed contents of the Inky EEPROM on the I2C bus at the argument path, e.g. `/dev/i2c-1`.
+pub fn read_eeprom_raw<P: AsRef<Path>>(path: P) -> io::Result<[u8; EEPROM_LENGTH]> {
+    let mut buf = [0u8; EEPROM_LENGTH];
+
+    let mut dev = LinuxI2CDevice::new(path, EEPROM_ADDRESS)?;
+
+    // Set the 16-bit read address back to the start of the EEPROM before reading
+    dev.write(&[0x00, 0x00])?;
+    dev.read(&mut buf)?;
+
+    Ok(buf)
+}
+
+/// Interpret the raw contents of an Inky EEPROM, e.g. as returned by [`read_eeprom_raw`] or captured elsewhere.
+/// Buffers shorter than [`EEPROM_LENGTH`] are invalid, any bytes beyond it are ignored. Display variants this
+/// crate doesn't know of are still parsed, [`EepromInfo::display_spec`] tells whether the display is supported.
+pub fn parse_eeprom(buf: &[u8]) -> Result<EepromInfo, EepromParseError> {
+    let buf = buf.get(..EEPROM_LENGTH).ok_or(EepromParseError::Invalid)?;
+
+    if buf.iter().all(|&b| b == 0xFF) || buf.iter().all(|&b| b == 0x00) {
+        return Err(EepromParseError::Blank);
+    }
+
+    let info = EepromInfo {
+        width: u16::from_le_bytes([buf[0], buf[1]]),
+        height: u16::from_le_bytes([buf[2], buf[3]]),
+        color: buf[4],
+        pcb_variant: buf[5],
+        display_variant: buf[6],
+        write_time: parse_write_time(&buf[WRITE_TIME_OFFSET..]),
+    };
+
+    if info.width == 0 || info.height == 0 {
+        return Err(EepromParseError::Invalid);
+    }
+
+    Ok(info)
+}
+
+/// Parse the length-prefixed write timestamp string, the length byte can be at most the space remaining after it.
+fn parse_write_time(field: &[u8]) -> Option<String> {
+    let (&len, rest) = field.split_first()?;
+    let bytes = rest.get(..len as usize)?;
+
+    if bytes.is_empty() {
+        return None;
+    }
+
+    Some(String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// The result of reading the Inky EEPROM on a single I2C bus.
+#[derive(Debug)]
+pub struct I2cBusReport {
+    pub path: Utf8PathBuf,
+    pub status: I2cProbeStatus,
+}
+
+/// Results of reading the Inky EEPROM on every I2C bus on the system.
+#[derive(Debug)]
+pub struct I2cBusReports(Vec<I2cBusReport>);
+
+impl I2cBusReports {
+    /// Attempt to read an Inky EEPROM on each of the I2C buses at `/dev/i2c-*`.
+    pub fn probe() -> Self {
+        Self::probe_paths(list_matching(Utf8PathBuf::from("/dev"), "i2c-"))
+    }
+
+    /// Attempt to read an Inky EEPROM on each of the argument I2C buses.
+    pub fn probe_paths(bus_paths: impl IntoIterator<Item = Utf8PathBuf>) -> Self {
+        let reports = bus_paths
+            .into_iter()
+            .map(|path| {
+                let status = read_eeprom(&path);
+                I2cBusReport { path, status }
+            })
+            .collect();
+
+        Self(reports)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &I2cBusReport> {
+        self.0.iter()
+    }
+}
+
+impl fmt::Display for I2cBusReports {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut builder = Builder::new();
+
+        if self.0.is_empty() {
+            return write!(f, "No I2C buses discovered");
+        }
+
+        builder.push_record(["bus", "status", "variant", "resolution", "written"]);
+
+        for report in self.0.iter() {
+            let (status, variant, res, written) = match &report.status {
+                I2cProbeStatus::Detected(info) => (
+                    String::from("detected"),
+                    info.variant_name()
+                        .map(String::from)
+                        .unwrap_or_else(|| format!("unknown ({})", info.display_variant)),
+                    format!("{}x{}", info.width, info.height),
+                    info.write_time.clone().unwrap_or_else(|| "-".into()),
+                ),
+                I2cProbeStatus::Unparseable(EepromParseError::Blank) => (
+                    String::from("blank EEPROM"),
+                    "-".into(),
+                    "-".into(),
+                    "-".into(),
+                ),
+                I2cProbeStatus::Unparseable(EepromParseError::Invalid) => (
+                    String::from("invalid EEPROM"),
+                    "-".into(),
+                    "-".into(),
+                    "-".into(),
+                ),
+                I2cProbeStatus::NoResponse(e) => {
+                    (format!("error: {}", e), "-".into(), "-".into(), "-".into())
+                }
+            };
+
+            builder.push_record([report.path.to_string(), status, variant, res, written]);
+        }
+
+        let table = builder.build();
+        write!(f, "{}", table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A JD79668 wHAT EEPROM, 400x300 programmed with a write timestamp.
+    fn jd79668_dump() -> [u8; EEPROM_LENGTH] {
+        let mut buf = [0u8; EEPROM_LENGTH];
+        buf[..7].copy_from_slice(&[0x90, 0x01, 0x2C, 0x01, 0x05, 0x0C, 24]);
+
+        let time = b"2025-03-14 10:22:31.5";
+        buf[7] = time.len() as u8;
+        buf[8..8 + time.len()].copy_from_slice(time);
+
+        buf
+    }
+
+    #[test]
+    fn parse_jd79668() {
+        let info = parse_eeprom(&jd79668_dump()).unwrap();
+
+        assert_eq!(info.resolution(), Resolution::new(400, 300));
+        assert_eq!(info.display_spec(), Some(DisplaySpec::Jd79668));
+        assert_eq!(info.variant_name(), Some("Red/Yellow wHAT (JD79668)"));
+        assert_eq!(info.write_time.as_deref(), Some("2025-03-14 10:22:31.5"));
+    }
+
+    #[test]
+    fn parse_write_time_out_of_range() {
+        let mut buf = jd79668_dump();
+        buf[7] = 0xFF;
+
+        let info = parse_eeprom(&buf).unwrap();
+
+        assert_eq!(info.write_time, None);
+    }
+
+    #[test]
+    fn parse_unknown_variant() {
+        let mut buf = jd79668_dump();
+        buf[6] = 200;
+
+        let info = parse_eeprom(&buf).unwrap();
+
+        assert_eq!(info.resolution(), Resolution::new(400, 300));
+        assert_eq!(info.variant_name(), None);
+        assert_eq!(info.display_spec(), None);
+        assert_eq!(info.write_time.as_deref(), Some("2025-03-14 10:22:31.5"));
+    }
+
+    #[test]
+    fn parse_blank() {
+        assert_eq!(
+            parse_eeprom(&[0xFF; EEPROM_LENGTH]),
+            Err(EepromParseError::Blank)
+        );
+        assert_eq!(
+            parse_eeprom(&[0x00; EEPROM_LENGTH]),
+            Err(EepromParseError::Blank)
+        );
+    }
+
+    #[test]
+    fn parse_short_buffer() {
+        let buf = jd79668_dump();
+
+        assert_eq!(parse_eeprom(&buf[..6]), Err(EepromParseError::Invalid));
+    }
+}
@@ -1,7 +1,7 @@
 use std::time::Duration;
 use thiserror::Error;
 
-use crate::Resolution;
+use crate::{EepromParseError, Resolution};
 
 /// Error variants for interacting with Inky displays.
 #[derive(Debug, Error)]
@@ -13,6 +13,9 @@ pub enum InkyError {
     #[error("GPIO error: {0}")]
     GpioError(#[from] gpiocdev::Error),
 
+    #[error("I2C IO error: {0}")]
+    I2cIoError(std::io::Error),
+
     #[error("SPI IO error: {0}")]
     SpiIoError(#[from] std::io::Error),
 
@@ -32,6 +35,15 @@ pub enum InkyError {
         index_max: usize,
     },
 
+    #[error("{bits_per_pixel} bits per pixel is unsupported, pixels must pack evenly into bytes")]
+    UnsupportedBitDepth { bits_per_pixel: u8 },
+
+    #[error("image buffer has {found} pixels, expected {expected} for the image resolution")]
+    InvalidBufferLength { expected: usize, found: usize },
+
+    #[error("color palettes must have between 1 and 256 colors, found {len}")]
+    InvalidPalette { len: usize },
+
     #[error("error during image encoding or decoding: {0}")]
     ImageError(#[from] image::ImageError),
 
@@ -41,8 +53,44 @@ pub enum InkyError {
         found: Resolution,
     },
 
+    #[error(
+        "a {}x{} region at ({}, {}) does not fit within the {}x{} display",
+        region.width, region.height, origin.0, origin.1, display.width, display.height
+    )]
+    RegionOutOfBounds {
+        origin: (u32, u32),
+        region: Resolution,
+        display: Resolution,
+    },
+
+    #[error(
+        "region horizontal origin {} and width {} must both be multiples of {} pixels",
+        x,
+        width,
+        alignment
+    )]
+    UnalignedRegion { x: u32, width: u32, alignment: u32 },
+
+    #[error(
+        "text scale {} is invalid, it must be at least 1 and fit a single glyph in the {}x{} image",
+        scale, res.width, res.height
+    )]
+    InvalidTextScale { scale: u32, res: Resolution },
+
     #[error("color to be rendered was outside of the supported color palette")]
     OutOfPaletteError,
+
+    #[error(transparent)]
+    Eeprom(#[from] EepromParseError),
+
+    #[error("display variant {} ({}) is not supported by this driver", variant, name.unwrap_or("unknown"))]
+    UnsupportedDisplay {
+        /// The display variant byte read from the EEPROM
+        variant: u8,
+
+        /// The human-readable name of the display variant, if known
+        name: Option<&'static str>,
+    },
 }
 
 pub type InkyResult<T> = Result<T, InkyError>;
@@ -1,8 +1,10 @@
-use crate::{InkyResult, Resolution};
+use crate::{InkyError, InkyResult, Resolution, TextOptions, render_text};
 use camino::Utf8PathBuf;
 use image::Pixel;
+use image::imageops::FilterType;
 use image::imageops::colorops::{ColorMap, index_colors};
-use image::{DynamicImage, ImageBuffer, ImageReader, Luma};
+use image::{DynamicImage, ImageBuffer, ImageReader, Luma, RgbImage};
+use std::borrow::Cow;
 
 /// An image buffer which contains 1 byte per pixel, with each pixel referring to an index in a color palette,
 /// not actually "luminance".
@@ -11,9 +13,111 @@ type IndexImage = ImageBuffer<Luma<u8>, Vec<u8>>;
 // Re-export Rgb, we leak that to the public interface
 pub use image::Rgb;
 
+/// Strategies for fitting an input image to the desired resolution of the display.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum FitStrategy {
+    /// The input image must already be the desired resolution, anything else is an error.
+    #[default]
+    Exact,
+
+    /// Scale the image to fit entirely within the desired resolution, preserving aspect ratio, and
+    /// letterbox the remainder with the palette color nearest to white.
+    Contain,
+
+    /// Scale the image to cover the desired resolution, preserving aspect ratio, and center-crop
+    /// whatever overhangs.
+    Cover,
+
+    /// Resize the image to exactly the desired resolution, ignoring aspect ratio.
+    Stretch,
+}
+
+/// Clockwise rotation applied to input images, for displays mounted in orientations other than landscape.
+///
+/// Rotation happens before the image is fit to the desired resolution, so for [`Rotation::R90`] and
+/// [`Rotation::R270`] a portrait source (e.g. 300x400) matches a landscape display (e.g. 400x300).
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Rotation {
+    #[default]
+    R0,
+    R90,
+    R180,
+    R270,
+}
+
+/// Dithering applied to images as part of color quantization.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum DitherMode {
+    /// Simple color quantization, each pixel maps to its nearest palette color
+    #[default]
+    None,
+
+    /// Floyd-Steinberg error diffusion
+    FloydSteinberg,
+
+    /// Ordered dithering against a Bayer threshold matrix. Stable and tileable, without the smearing and
+    /// "worming" error diffusion can produce on flat gradients.
+    Ordered(BayerMatrix),
+}
+
+/// Sizes of Bayer threshold matrix for [`DitherMode::Ordered`]. Larger matrices reproduce more intermediate
+/// shades at the cost of a coarser-looking pattern.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum BayerMatrix {
+    Bayer2x2,
+    #[default]
+    Bayer4x4,
+    Bayer8x8,
+}
+
+impl BayerMatrix {
+    /// Number of bits needed to index a row or column of the matrix.
+    fn order(self) -> u32 {
+        match self {
+            BayerMatrix::Bayer2x2 => 1,
+            BayerMatrix::Bayer4x4 => 2,
+            BayerMatrix::Bayer8x8 => 3,
+        }
+    }
+
+    /// Number of cells in the matrix, each holds a distinct value in `0..cells`.
+    fn cells(self) -> u32 {
+        1 << (2 * self.order())
+    }
+
+    /// The matrix value at the argument pixel coordinates, the matrix is tiled across the image.
+    fn value(self, x: u32, y: u32) -> u32 {
+        // Each level of the matrix is built from the one below it as [[4M, 4M+2], [4M+3, 4M+1]], so the value
+        // is accumulated from the base 2x2 pattern at each bit of the coordinates, least significant first.
+        const BASE: [[u32; 2]; 2] = [[0, 2], [3, 1]];
+
+        (0..self.order()).fold(0, |value, bit| {
+            let bx = ((x >> bit) & 1) as usize;
+            let by = ((y >> bit) & 1) as usize;
+            value * 4 + BASE[by][bx]
+        })
+    }
+
+    /// The threshold offset for the argument pixel coordinates, in (-0.5, 0.5).
+    fn threshold(self, x: u32, y: u32) -> f32 {
+        (self.value(x, y) as f32 + 0.5) / self.cells() as f32 - 0.5
+    }
+}
+
+/// Magnitude of the offset applied to each color channel by ordered dithering, a full channel's worth since
+/// palettes like ours sit at the corners of the RGB cube.
+const ORDERED_DITHER_SPREAD: f32 = 255.0;
+
+/// Resampling filter used whenever an image is resized to fit the display.
+const RESIZE_FILTER: FilterType = FilterType::Lanczos3;
+
 pub struct ImagePreProcessor<CMap: ColorMap<Color = Rgb<u8>>> {
     pub color_map: CMap,
     pub desired_res: Resolution,
+    pub fit: FitStrategy,
+    pub rotation: Rotation,
+    pub flip_horizontal: bool,
+    pub flip_vertical: bool,
 }
 
 impl<CMap: ColorMap<Color = Rgb<u8>>> ImagePreProcessor<CMap> {
@@ -21,25 +125,47 @@ impl<CMap: ColorMap<Color = Rgb<u8>>> ImagePreProcessor<CMap> {
         Self {
             color_map,
             desired_res,
+            fit: FitStrategy::default(),
+            rotation: Rotation::default(),
+            flip_horizontal: false,
+            flip_vertical: false,
         }
     }
 
-    /// Preprocess the argument [`DynamicImage`] performing color quantization and optionally dithering.
-    pub fn prepare(&self, img: &DynamicImage, dither: bool) -> InkyResult<InkyImage> {
-        let input_res = Resolution::new(img.width(), img.height());
+    /// Set the [`FitStrategy`] used for input images which don't match the desired resolution.
+    pub fn with_fit(mut self, fit: FitStrategy) -> Self {
+        self.fit = fit;
+        self
+    }
 
-        // In the future we could do some kind of intelligent resizing or something, but for now just
-        // throw an error if we don't get the native resolution of the display we're using.
-        if input_res != self.desired_res {
-            return Err(crate::InkyError::UnsupportedResolution {
-                expected: self.desired_res,
-                found: input_res,
-            });
-        }
+    /// Set the [`Rotation`] applied to input images.
+    pub fn with_rotation(mut self, rotation: Rotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Set whether input images are mirrored horizontally and/or vertically, flips are applied after rotation.
+    pub fn with_flip(mut self, horizontal: bool, vertical: bool) -> Self {
+        self.flip_horizontal = horizontal;
+        self.flip_vertical = vertical;
+        self
+    }
+
+    /// Preprocess the argument [`DynamicImage`], rotating and flipping it, fitting it to the desired resolution
+    /// according to the configured [`FitStrategy`], then performing color quantization with the argument
+    /// [`DitherMode`].
+    pub fn prepare(&self, img: &DynamicImage, dither: DitherMode) -> InkyResult<InkyImage> {
+        let oriented = self.orient_image(img);
+        let rgb = &mut self.fit_image(&oriented)?;
+        self.quantize(rgb, dither)
+    }
 
-        let rgb = &mut img.to_rgb8();
-        if dither {
-            image::imageops::dither(rgb, &self.color_map);
+    /// Quantize an image of the desired resolution into the color map, with the argument [`DitherMode`].
+    fn quantize(&self, rgb: &mut RgbImage, dither: DitherMode) -> InkyResult<InkyImage> {
+        match dither {
+            DitherMode::None => {}
+            DitherMode::FloydSteinberg => image::imageops::dither(rgb, &self.color_map),
+            DitherMode::Ordered(matrix) => self.ordered_dither(rgb, matrix),
         }
         let index_image = index_colors(rgb, &self.color_map);
 
@@ -49,6 +175,78 @@ impl<CMap: ColorMap<Color = Rgb<u8>>> ImagePreProcessor<CMap> {
         Ok(InkyImage::new(index_image, mapped))
     }
 
+    /// Ordered dithering, image::imageops only provides error diffusion for arbitrary [`ColorMap`]s. Each pixel
+    /// is offset by the Bayer threshold at its position before being mapped to its nearest palette color.
+    fn ordered_dither(&self, rgb: &mut RgbImage, matrix: BayerMatrix) {
+        for (x, y, pixel) in rgb.enumerate_pixels_mut() {
+            let offset = matrix.threshold(x, y) * ORDERED_DITHER_SPREAD;
+
+            for channel in pixel.0.iter_mut() {
+                *channel = (*channel as f32 + offset).round().clamp(0.0, 255.0) as u8;
+            }
+
+            self.color_map.map_color(pixel);
+        }
+    }
+
+    /// Apply the configured [`Rotation`] and flips to the argument image, borrowing it if there's nothing to do.
+    fn orient_image<'a>(&self, img: &'a DynamicImage) -> Cow<'a, DynamicImage> {
+        let mut oriented = match self.rotation {
+            Rotation::R0 => Cow::Borrowed(img),
+            Rotation::R90 => Cow::Owned(img.rotate90()),
+            Rotation::R180 => Cow::Owned(img.rotate180()),
+            Rotation::R270 => Cow::Owned(img.rotate270()),
+        };
+
+        if self.flip_horizontal {
+            oriented = Cow::Owned(oriented.fliph());
+        }
+
+        if self.flip_vertical {
+            oriented = Cow::Owned(oriented.flipv());
+        }
+
+        oriented
+    }
+
+    /// Fit the argument image to the desired resolution according to the configured [`FitStrategy`].
+    fn fit_image(&self, img: &DynamicImage) -> InkyResult<RgbImage> {
+        let input_res = Resolution::new(img.width(), img.height());
+        let Resolution { width, height } = self.desired_res;
+
+        if input_res == self.desired_res {
+            return Ok(img.to_rgb8());
+        }
+
+        let fitted = match self.fit {
+            FitStrategy::Exact => {
+                return Err(InkyError::UnsupportedResolution {
+                    expected: self.desired_res,
+                    found: input_res,
+                });
+            }
+            FitStrategy::Contain => {
+                let scaled = img.resize(width, height, RESIZE_FILTER).to_rgb8();
+
+                // Letterbox with a color that belongs to the palette so the fill quantizes cleanly
+                let fill = self
+                    .color_map
+                    .lookup(self.color_map.index_of(&Rgb([255, 255, 255])))
+                    .expect("indexed color out-of-range");
+
+                let mut canvas = RgbImage::from_pixel(width, height, fill);
+                let x = (width - scaled.width()) / 2;
+                let y = (height - scaled.height()) / 2;
+                image::imageops::overlay(&mut canvas, &scaled, x.into(), y.into());
+                canvas
+            }
+            FitStrategy::Cover => img.resize_to_fill(width, height, RESIZE_FILTER).to_rgb8(),
+            FitStrategy::Stretch => img.resize_exact(width, height, RESIZE_FILTER).to_rgb8(),
+        };
+
+        Ok(fitted)
+    }
+
     /// Given an [`IndexImage`], map back into a "colorspace" image using the color map for this [`ImagePreProcessor`].
     fn map_index_image(&self, index_image: &IndexImage) -> InkyResult<DynamicImage> {
         // Remap to a colorspace we can encode for saving prepared images to the filesystem
@@ -63,9 +261,13 @@ impl<CMap: ColorMap<Color = Rgb<u8>>> ImagePreProcessor<CMap> {
         Ok(DynamicImage::from(mapped))
     }
 
-    /// Preprocess the image file at the argument filepath, performing color quantization and optionally dithering.
+    /// Preprocess the image file at the argument filepath, see [`Self::prepare`].
     /// Jpegs, PNGs, and BMPs are supported.
-    pub fn prepare_from_path(&self, path: Utf8PathBuf, dither: bool) -> InkyResult<InkyImage> {
+    pub fn prepare_from_path(
+        &self,
+        path: Utf8PathBuf,
+        dither: DitherMode,
+    ) -> InkyResult<InkyImage> {
         let img = ImageReader::open(path)?.decode()?;
         self.prepare(&img, dither)
     }
@@ -84,6 +286,23 @@ impl<CMap: ColorMap<Color = Rgb<u8>>> ImagePreProcessor<CMap> {
 
         Ok(InkyImage::new(index_image, mapped))
     }
+
+    /// Generates an image of the correct dimensions containing the argument text rendered with the bundled
+    /// bitmap font, and then quantizes it using the configured color map. Text is wrapped at the right edge
+    /// of the image and clipped at the bottom.
+    ///
+    /// Returns an error if the foreground or background colors don't belong to the color palette, or if the
+    /// scale is zero or too large for a single glyph to fit in the image.
+    pub fn new_text(&self, text: &str, opts: TextOptions) -> InkyResult<InkyImage> {
+        for color in [opts.foreground, opts.background] {
+            if self.color_map.lookup(self.color_map.index_of(&color)) != Some(color) {
+                return Err(InkyError::OutOfPaletteError);
+            }
+        }
+
+        let rgb = &mut render_text(text, &opts, self.desired_res)?;
+        self.quantize(rgb, DitherMode::None)
+    }
 }
 
 /// A pre-processed image containing an [`IndexImage`] ready to be bit-packed and serialized for the display,
@@ -126,3 +345,283 @@ impl InkyImage {
         Resolution::new(w, h)
     }
 }
+
+/// Pack a palletized image with the argument resolution into a flattened buffer of `bits_per_pixel` bits per
+/// pixel to be sent to a display. `bits_per_pixel` must be one of 1, 2, 4, or 8 so that pixels pack evenly into
+/// bytes, see [`crate::PaletteColorMap::bits_per_pixel`].
+///
+/// Pixels are packed most-significant bits first, e.g. for 2 bits per pixel each byte contains 4 pixels:
+/// [p0 p1 p2 p3] → (p0<<6) | (p1<<4) | (p2<<2) | (p3)
+///
+/// If the number of pixels isn't a multiple of the pixels per byte, the unused low bits of the final byte are
+/// zeroed, i.e. padded with palette index 0.
+///
+/// Returns an error if `bits_per_pixel` is unsupported, if the number of pixels doesn't match the resolution, or
+/// if any pixel value doesn't fit in `bits_per_pixel` bits.
+pub fn pack_buffer(pixels: &[u8], res: Resolution, bits_per_pixel: u8) -> InkyResult<Vec<u8>> {
+    if !matches!(bits_per_pixel, 1 | 2 | 4 | 8) {
+        return Err(InkyError::UnsupportedBitDepth { bits_per_pixel });
+    }
+
+    let expected_len = res.width as usize * res.height as usize;
+    if pixels.len() != expected_len {
+        return Err(InkyError::InvalidBufferLength {
+            expected: expected_len,
+            found: pixels.len(),
+        });
+    }
+
+    let index_max = (1usize << bits_per_pixel) - 1;
+    if !pixels.iter().all(|&p| (p as usize) <= index_max) {
+        return Err(InkyError::InvalidPalettization {
+            index_min: 0,
+            index_max,
+        });
+    }
+
+    let pixels_per_byte = (8 / bits_per_pixel) as usize;
+    let mut out = Vec::with_capacity(pixels.len().div_ceil(pixels_per_byte));
+
+    for chunk in pixels.chunks(pixels_per_byte) {
+        let byte = chunk.iter().enumerate().fold(0u8, |byte, (i, &p)| {
+            let shift = 8 - bits_per_pixel as usize * (i + 1);
+            byte | (p << shift)
+        });
+
+        out.push(byte);
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{InkyFourColorMap, MonoColorMap};
+
+    #[test]
+    fn pack_exact_multiple_of_four() {
+        let pixels = [0, 1, 2, 3, 3, 2, 1, 0];
+        let packed = pack_buffer(&pixels, Resolution::new(4, 2), 2).unwrap();
+
+        assert_eq!(packed, vec![0b00_01_10_11, 0b11_10_01_00]);
+    }
+
+    #[test]
+    fn pack_trailing_chunk_is_zero_padded() {
+        let cases: [(&[u8], u8); 3] = [
+            (&[0, 1, 2, 3, 3], 0b11_00_00_00),
+            (&[0, 1, 2, 3, 3, 2], 0b11_10_00_00),
+            (&[0, 1, 2, 3, 3, 2, 1], 0b11_10_01_00),
+        ];
+
+        for (pixels, last) in cases {
+            let res = Resolution::new(pixels.len() as u32, 1);
+            let packed = pack_buffer(pixels, res, 2).unwrap();
+
+            assert_eq!(packed, vec![0b00_01_10_11, last]);
+        }
+    }
+
+    #[test]
+    fn pack_empty() {
+        let packed = pack_buffer(&[], Resolution::new(0, 0), 2).unwrap();
+
+        assert!(packed.is_empty());
+    }
+
+    #[test]
+    fn pack_other_bit_depths() {
+        let mono = pack_buffer(&[1, 0, 1, 1, 0, 0, 0, 1, 1], Resolution::new(3, 3), 1).unwrap();
+        assert_eq!(mono, vec![0b1011_0001, 0b1000_0000]);
+
+        let nibbles = pack_buffer(&[0xA, 0x5, 0xF], Resolution::new(3, 1), 4).unwrap();
+        assert_eq!(nibbles, vec![0xA5, 0xF0]);
+    }
+
+    #[test]
+    fn pack_out_of_palette_pixel() {
+        let err = pack_buffer(&[0, 1, 4, 3], Resolution::new(4, 1), 2).unwrap_err();
+
+        assert!(matches!(
+            err,
+            InkyError::InvalidPalettization {
+                index_min: 0,
+                index_max: 3
+            }
+        ));
+    }
+
+    #[test]
+    fn pack_unsupported_bit_depth() {
+        let err = pack_buffer(&[0, 1, 2, 3], Resolution::new(4, 1), 3).unwrap_err();
+
+        assert!(matches!(
+            err,
+            InkyError::UnsupportedBitDepth { bits_per_pixel: 3 }
+        ));
+    }
+
+    #[test]
+    fn pack_mismatched_length() {
+        let err = pack_buffer(&[0, 1, 2], Resolution::new(2, 2), 2).unwrap_err();
+
+        assert!(matches!(
+            err,
+            InkyError::InvalidBufferLength {
+                expected: 4,
+                found: 3
+            }
+        ));
+    }
+
+    const RED: Rgb<u8> = Rgb([255, 0, 0]);
+    const WHITE: Rgb<u8> = Rgb([255, 255, 255]);
+
+    /// A solid red image, red being a palette color so it survives quantization unchanged.
+    fn red_image(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::from(RgbImage::from_pixel(width, height, RED))
+    }
+
+    fn landscape_preproc() -> ImagePreProcessor<InkyFourColorMap> {
+        ImagePreProcessor::new(InkyFourColorMap, Resolution::new(400, 300))
+    }
+
+    #[test]
+    fn fit_exact_rejects_mismatch() {
+        let err = landscape_preproc()
+            .fit_image(&red_image(300, 400))
+            .unwrap_err();
+
+        assert!(matches!(
+            err,
+            InkyError::UnsupportedResolution { expected, found }
+                if expected == Resolution::new(400, 300) && found == Resolution::new(300, 400)
+        ));
+    }
+
+    #[test]
+    fn fit_contain_letterboxes_with_white() {
+        let preproc = landscape_preproc().with_fit(FitStrategy::Contain);
+        let fitted = preproc.fit_image(&red_image(300, 400)).unwrap();
+
+        // Scaled to 225x300 and centered, leaving bars down the left and right
+        assert_eq!(fitted.dimensions(), (400, 300));
+        assert_eq!(*fitted.get_pixel(0, 150), WHITE);
+        assert_eq!(*fitted.get_pixel(399, 150), WHITE);
+        assert_eq!(*fitted.get_pixel(200, 150), RED);
+    }
+
+    #[test]
+    fn fit_cover_and_stretch_fill_desired_res() {
+        for fit in [FitStrategy::Cover, FitStrategy::Stretch] {
+            let preproc = landscape_preproc().with_fit(fit);
+            let fitted = preproc.fit_image(&red_image(300, 400)).unwrap();
+
+            assert_eq!(fitted.dimensions(), (400, 300));
+            assert!(fitted.pixels().all(|&p| p == RED));
+        }
+    }
+
+    #[test]
+    fn rotation_fits_portrait_source_to_landscape_display() {
+        for rotation in [Rotation::R90, Rotation::R270] {
+            let preproc = landscape_preproc().with_rotation(rotation);
+            let prepared = preproc
+                .prepare(&red_image(300, 400), DitherMode::None)
+                .unwrap();
+
+            assert_eq!(prepared.resolution(), Resolution::new(400, 300));
+        }
+    }
+
+    #[test]
+    fn flips_apply_after_rotation() {
+        // A 2x3 white image with a red top-left corner
+        let mut src = RgbImage::from_pixel(2, 3, WHITE);
+        src.put_pixel(0, 0, RED);
+        let src = DynamicImage::from(src);
+
+        // Rotating clockwise moves the corner to the top-right, and the horizontal flip brings it back to the
+        // top-left. Flipping first would instead land it at (2, 1).
+        let preproc = ImagePreProcessor::new(InkyFourColorMap, Resolution::new(3, 2))
+            .with_rotation(Rotation::R90)
+            .with_flip(true, false);
+        let oriented = preproc.orient_image(&src).to_rgb8();
+
+        assert_eq!(oriented.dimensions(), (3, 2));
+        assert_eq!(*oriented.get_pixel(0, 0), RED);
+        assert_eq!(*oriented.get_pixel(2, 1), WHITE);
+    }
+
+    #[test]
+    fn new_text_rejects_off_palette_colors() {
+        let off_palette = Rgb([1, 2, 3]);
+        let cases = [
+            TextOptions {
+                foreground: off_palette,
+                ..Default::default()
+            },
+            TextOptions {
+                background: off_palette,
+                ..Default::default()
+            },
+        ];
+
+        for opts in cases {
+            assert!(matches!(
+                landscape_preproc().new_text("hi", opts),
+                Err(InkyError::OutOfPaletteError)
+            ));
+        }
+    }
+
+    #[test]
+    fn bayer_matrices_are_permutations() {
+        for matrix in [
+            BayerMatrix::Bayer2x2,
+            BayerMatrix::Bayer4x4,
+            BayerMatrix::Bayer8x8,
+        ] {
+            let size = 1 << matrix.order();
+            let mut values: Vec<u32> = (0..size)
+                .flat_map(|y| (0..size).map(move |x| matrix.value(x, y)))
+                .collect();
+            values.sort_unstable();
+
+            assert_eq!(values, (0..matrix.cells()).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn bayer_4x4_is_the_standard_matrix() {
+        let expected = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+        for (y, row) in expected.iter().enumerate() {
+            for (x, &value) in row.iter().enumerate() {
+                assert_eq!(BayerMatrix::Bayer4x4.value(x as u32, y as u32), value);
+            }
+        }
+    }
+
+    #[test]
+    fn ordered_dither_mid_gray_is_half_white() {
+        let matrix = BayerMatrix::Bayer4x4;
+        let preproc = ImagePreProcessor::new(MonoColorMap, Resolution::new(8, 8));
+        let mut rgb = RgbImage::from_pixel(8, 8, Rgb([128, 128, 128]));
+        preproc.ordered_dither(&mut rgb, matrix);
+
+        // The upper half of the matrix values push mid-gray up to white and the lower half down to black, the
+        // same for every tile of the matrix across the image
+        for (x, y, &pixel) in rgb.enumerate_pixels() {
+            let expected = match matrix.value(x, y) >= matrix.cells() / 2 {
+                true => WHITE,
+                false => Rgb([0, 0, 0]),
+            };
+            assert_eq!(pixel, expected, "pixel ({x}, {y})");
+        }
+
+        let white = rgb.pixels().filter(|&&p| p == WHITE).count();
+        assert_eq!(white, 32);
+    }
+}
@@ -1,23 +1,35 @@
-use crate::{InkyError, InkyImage, InkyResult, Resolution};
+use crate::{
+    DisplaySpec, I2cProbeStatus, InkyError, InkyFourColorPalette, InkyImage, InkyResult,
+    Resolution, pack_buffer, read_eeprom,
+};
 use camino::Utf8PathBuf;
 use gpiocdev::Request;
-use gpiocdev::line::{Bias, Direction, Value};
+use gpiocdev::line::{Bias, Direction, EdgeDetection, Value};
 use log::debug;
 use serde::{Deserialize, Serialize};
 use spidev::{SpiModeFlags, Spidev, SpidevOptions};
 use std::io::Write;
+use std::path::Path;
 use std::thread;
 use std::time::{Duration, Instant};
 
-// TODO: need docs for these
-const X_ADDR_START_H: u8 = 0x01;
-const X_ADDR_START_L: u8 = 0x90;
-const Y_ADDR_START_H: u8 = 0x01;
-const Y_ADDR_START_L: u8 = 0x2C;
+/// The display packs each pixel of its four color palette into 2 bits.
+const BITS_PER_PIXEL: u8 = 2;
+
+/// Pixels packed into each byte of display memory, partial updates must be aligned to this horizontally.
+const PIXELS_PER_BYTE: u32 = 8 / BITS_PER_PIXEL as u32;
+
+/// Argument to [`Jd79668Commands::AutoSequence`] selecting power on → display refresh → power off → deep sleep,
+/// the same sequence [`InkyJd79668::show`] otherwise sends as separate commands. The other documented argument,
+/// 0xA5, stops after power off without entering deep sleep.
+const AUTO_SEQUENCE_REFRESH_SLEEP: u8 = 0xA7;
 
 /// Maximum size of a single SPI transmission frame in bytes.
 const SPI_CHUNK_SIZE: usize = 4096;
 
+/// A nice liberal timeout, most commands won't use anywhere near all of this except for the display refresh.
+const REFRESH_TIMEOUT: Duration = Duration::from_secs(40);
+
 /// Configuration for GPIOs required for Inky displays.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Jd79668GpiosConfig {
@@ -48,7 +60,8 @@ pub struct Jd79668Gpios {
     /// Hardware reset pin
     pub reset: Request,
 
-    /// Display busy indicator pin
+    /// Display busy indicator pin, with rising edge detection enabled so we can block on the display becoming
+    /// ready
     pub busy: Request,
 }
 
@@ -88,6 +101,7 @@ impl Jd79668Gpios {
             .with_line(cfg.busy)
             .with_direction(Direction::Input)
             .with_bias(Bias::PullUp)
+            .with_edge_detection(EdgeDetection::RisingEdge)
             .with_consumer("tatted-busy")
             .request()?;
 
@@ -110,18 +124,135 @@ pub enum Jd79668Commands {
     BoosterSoftStart = 0x06,
     DeepSleep = 0x07,
     DataStartTransmission = 0x10,
-    DataStopTransmission = 0x11, // TODO: unused currently
+    DataStopTransmission = 0x11,
     DisplayRefresh = 0x12,
-    AutoSequence = 0x17, // TODO: unused currently
+    AutoSequence = 0x17,
     VcomDataIntervalSetting = 0x50,
     ResolutionSetting = 0x61,
+    // The partial update commands follow the UC81xx controllers the JD79668 shares its command set with
+    PartialWindow = 0x90,
+    PartialIn = 0x91,
+    PartialOut = 0x92,
 }
 
+/// [`Jd79668Commands::VcomDataIntervalSetting`] data byte from the initialization sequence in the inky python
+/// lib, its border bits are replaced according to the [`BorderColor`].
+const VCOM_DATA_INTERVAL: u8 = 0x37;
+
+/// Color the display drives the border around the active area to.
+///
+/// The border is set by the top two bits of the [`Jd79668Commands::VcomDataIntervalSetting`] data byte,
+/// which take the same 2-bit encoding as pixels in display memory, the [`InkyFourColorPalette`] index:
+///
+/// | bits [7:6] | border |
+/// |------------|--------|
+/// | 0b00       | black  |
+/// | 0b01       | white  |
+/// | 0b10       | yellow |
+/// | 0b11       | red    |
+///
+/// The lower six bits are left at the VCOM and data interval from the python lib's 0x37, so the default black
+/// border sends exactly the byte the display has always been initialized with.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BorderColor {
+    #[default]
+    Black,
+    White,
+    Yellow,
+    Red,
+}
+
+impl From<InkyFourColorPalette> for BorderColor {
+    fn from(color: InkyFourColorPalette) -> Self {
+        match color {
+            InkyFourColorPalette::Black => BorderColor::Black,
+            InkyFourColorPalette::White => BorderColor::White,
+            InkyFourColorPalette::Yellow => BorderColor::Yellow,
+            InkyFourColorPalette::Red => BorderColor::Red,
+        }
+    }
+}
+
+impl From<BorderColor> for InkyFourColorPalette {
+    fn from(color: BorderColor) -> Self {
+        match color {
+            BorderColor::Black => InkyFourColorPalette::Black,
+            BorderColor::White => InkyFourColorPalette::White,
+            BorderColor::Yellow => InkyFourColorPalette::Yellow,
+            BorderColor::Red => InkyFourColorPalette::Red,
+        }
+    }
+}
+
+impl BorderColor {
+    /// The full [`Jd79668Commands::VcomDataIntervalSetting`] data byte for this border color.
+    fn vcom_data_interval(self) -> u8 {
+        let bits = InkyFourColorPalette::from(self) as u8;
+        (bits << 6) | (VCOM_DATA_INTERVAL & 0b0011_1111)
+    }
+}
+
+/// Default SPI clock speed, a conservative value which is known to work with the wHAT on a Raspberry Pi.
+const DEFAULT_SPI_MAX_SPEED_HZ: u32 = 1_000_000;
+
+/// Default delay after asserting chip select and data/command before each SPI write, stolen from the inky
+/// python lib.
+const DEFAULT_COMMAND_SETUP_DELAY: Duration = Duration::from_millis(300);
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Jd79668Config {
     pub display_res: Resolution,
     pub spi_path: Utf8PathBuf,
     pub gpios: Jd79668GpiosConfig,
+
+    /// Maximum SPI clock speed. Faster clocks shorten transmission of the image buffer, but push it too far for
+    /// your wiring and the display will silently receive corrupted commands and image data.
+    #[serde(default = "default_spi_max_speed_hz")]
+    pub spi_max_speed_hz: u32,
+
+    /// Delay after setting the chip select and data/command pins before writing a command byte, and again
+    /// before writing its data. Shrinking this speeds up every command, but too short a delay may not give the
+    /// controller time to latch the data/command pin, resulting in garbled commands.
+    #[serde(
+        default = "default_command_setup_delay",
+        rename = "command_setup_delay_ms",
+        with = "duration_millis"
+    )]
+    pub command_setup_delay: Duration,
+
+    /// Have the display run its power on, refresh, power off, deep sleep sequence itself via the Auto Sequence
+    /// command, rather than sending each step and waiting on the busy pin in between. Not yet validated on
+    /// hardware, so off by default.
+    #[serde(default)]
+    pub use_auto_sequence: bool,
+
+    /// Color of the border around the active area of the display
+    #[serde(default)]
+    pub border: BorderColor,
+}
+
+fn default_spi_max_speed_hz() -> u32 {
+    DEFAULT_SPI_MAX_SPEED_HZ
+}
+
+fn default_command_setup_delay() -> Duration {
+    DEFAULT_COMMAND_SETUP_DELAY
+}
+
+/// (De)serialize a [`Duration`] as an integer number of milliseconds, friendlier in a config file than serde's
+/// default seconds-and-nanoseconds struct.
+mod duration_millis {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_millis() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_millis)
+    }
 }
 
 impl Default for Jd79668Config {
@@ -138,6 +269,11 @@ impl Default for Jd79668Config {
                 reset: 27,
                 busy: 17,
             },
+
+            spi_max_speed_hz: DEFAULT_SPI_MAX_SPEED_HZ,
+            command_setup_delay: DEFAULT_COMMAND_SETUP_DELAY,
+            use_auto_sequence: false,
+            border: BorderColor::default(),
         }
     }
 }
@@ -147,10 +283,12 @@ pub struct InkyJd79668 {
     spi: Spidev,
     gpios: Jd79668Gpios,
     display_res: Resolution,
+    command_setup_delay: Duration,
+    use_auto_sequence: bool,
+    border: BorderColor,
     initialized: bool,
 }
 
-// TODO (tff): would like to incorporate the EEPROM read here as well
 impl InkyJd79668 {
     /// Take ownership of all required peripheral hardware and return a new [`InkyJd79668`].
     ///
@@ -168,7 +306,7 @@ impl InkyJd79668 {
         let mut spi = Spidev::open(cfg.spi_path)?;
         let options = SpidevOptions::new()
             .bits_per_word(8)
-            .max_speed_hz(1_000_000)
+            .max_speed_hz(cfg.spi_max_speed_hz)
             .mode(SpiModeFlags::SPI_MODE_0 | SpiModeFlags::SPI_NO_CS)
             .build();
         spi.configure(&options)?;
@@ -179,10 +317,46 @@ impl InkyJd79668 {
             spi,
             gpios,
             display_res: cfg.display_res,
+            command_setup_delay: cfg.command_setup_delay,
+            use_auto_sequence: cfg.use_auto_sequence,
+            border: cfg.border,
             initialized: false,
         })
     }
 
+    /// Like [`Self::new`], but the display resolution is read from the EEPROM on the I2C bus at the argument
+    /// path rather than taken from the [`Jd79668Config`], whose `display_res` is ignored.
+    ///
+    /// Returns an error if the EEPROM is blank, unreadable, or describes a display other than the JD79668.
+    pub fn from_eeprom<P: AsRef<Path>>(cfg: Jd79668Config, i2c_path: P) -> InkyResult<Self> {
+        debug!("reading display EEPROM");
+
+        let info = match read_eeprom(i2c_path) {
+            I2cProbeStatus::Detected(info) => info,
+            I2cProbeStatus::Unparseable(e) => return Err(e.into()),
+            I2cProbeStatus::NoResponse(e) => return Err(InkyError::I2cIoError(e)),
+        };
+
+        if info.display_spec() != Some(DisplaySpec::Jd79668) {
+            return Err(InkyError::UnsupportedDisplay {
+                variant: info.display_variant,
+                name: info.variant_name(),
+            });
+        }
+
+        debug!("EEPROM reports a {}x{} display", info.width, info.height);
+
+        Self::new(Jd79668Config {
+            display_res: info.resolution(),
+            ..cfg
+        })
+    }
+
+    /// Returns the resolution of the display
+    pub fn display_res(&self) -> Resolution {
+        self.display_res
+    }
+
     /// Perform a hardware reset
     pub fn hardware_reset(&mut self) -> InkyResult<()> {
         debug!("performing a display hardware reset");
@@ -215,15 +389,13 @@ impl InkyJd79668 {
             Some(&[0x0d, 0x12, 0x24, 0x25, 0x12, 0x29, 0x10]),
         )?;
         self.send_command(0x30, Some(&[0x08]))?;
-        self.send_command(Cmd::VcomDataIntervalSetting as u8, Some(&[0x37]))?;
+        self.send_command(
+            Cmd::VcomDataIntervalSetting as u8,
+            Some(&[self.border.vcom_data_interval()]),
+        )?;
         self.send_command(
             Cmd::ResolutionSetting as u8,
-            Some(&[
-                X_ADDR_START_H,
-                X_ADDR_START_L,
-                Y_ADDR_START_H,
-                Y_ADDR_START_L,
-            ]),
+            Some(&Self::resolution_setting(self.display_res)),
         )?;
 
         // TODO: better docs here
@@ -238,12 +410,38 @@ impl InkyJd79668 {
         Ok(())
     }
 
+    /// Set the color of the border around the active area of the display, it takes effect on the next refresh.
+    /// While the display is uninitialized or asleep after a refresh the color is only stored, and
+    /// [`Self::initialize`] applies it, otherwise the border register is rewritten immediately.
+    pub fn set_border(&mut self, color: BorderColor) -> InkyResult<()> {
+        self.border = color;
+
+        // The display ignores commands in deep sleep, the next initialization sends the stored color instead
+        if self.initialized {
+            self.send_command(
+                Jd79668Commands::VcomDataIntervalSetting as u8,
+                Some(&[color.vcom_data_interval()]),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Data bytes for the [`Jd79668Commands::ResolutionSetting`] command: the horizontal then vertical
+    /// resolution, each as a big-endian u16.
+    fn resolution_setting(res: Resolution) -> [u8; 4] {
+        let [w_h, w_l] = (res.width as u16).to_be_bytes();
+        let [h_h, h_l] = (res.height as u16).to_be_bytes();
+
+        [w_h, w_l, h_h, h_l]
+    }
+
     /// Send a SPI command
     fn send_command(&mut self, command: u8, data: Option<&[u8]>) -> InkyResult<()> {
         self.gpios.chip_select.set_lone_value(Value::Inactive)?;
         self.gpios.data_cmd.set_lone_value(Value::Inactive)?;
 
-        thread::sleep(Duration::from_millis(300));
+        thread::sleep(self.command_setup_delay);
 
         self.spi.write_all(&[command])?;
 
@@ -251,7 +449,7 @@ impl InkyJd79668 {
         if let Some(data) = data {
             self.gpios.data_cmd.set_lone_value(Value::Active)?;
 
-            thread::sleep(Duration::from_millis(300));
+            thread::sleep(self.command_setup_delay);
 
             // Chunk up the write if necessary
             if data.len() <= SPI_CHUNK_SIZE {
@@ -277,19 +475,24 @@ impl InkyJd79668 {
         // where if we call busy_wait() and the display is immediately "not busy", we wait for
         // an arbitrary time out anyway. This doesn't seem to be required in practice so I'm leaving
         // it out here.
-        while start.elapsed() < timeout {
-            match self.gpios.busy.lone_value()? {
-                // Display is busy
-                Value::Inactive => {
-                    // TODO (tff): there's almost certainly a better API in gpiocdev for doing this without a hotloop
-                    thread::sleep(Duration::from_millis(10));
-                }
-                // Display is not busy
-                Value::Active => return Ok(()),
+        loop {
+            // Discard any edges queued up since the last wait, the line value is the source of truth
+            while self.gpios.busy.has_edge_event()? {
+                self.gpios.busy.read_edge_event()?;
             }
-        }
 
-        Err(InkyError::BusyTimeout { timeout })
+            // The display is not busy
+            if self.gpios.busy.lone_value()? == Value::Active {
+                return Ok(());
+            }
+
+            // Block until the busy line rises. An edge arriving between reading the value and waiting is
+            // still queued by the kernel, so it can't be missed.
+            let remaining = timeout.saturating_sub(start.elapsed());
+            if remaining.is_zero() || !self.gpios.busy.wait_edge_event(remaining)? {
+                return Err(InkyError::BusyTimeout { timeout });
+            }
+        }
     }
 
     /// Send a SPI command and then wait for the busy GPIO pin to indicate that the display is ready for new data
@@ -306,7 +509,8 @@ impl InkyJd79668 {
         Ok(())
     }
 
-    /// Refresh the display with the [`InkyImage`], this clones the stored palletized image.
+    /// Refresh the display with the [`InkyImage`], this clones the stored palletized image. The display is left
+    /// in deep sleep afterwards, so it must be initialized again before showing another image.
     pub fn show(&mut self, img: &InkyImage) -> InkyResult<()> {
         use Jd79668Commands as Cmd;
 
@@ -324,55 +528,267 @@ impl InkyJd79668 {
             });
         }
 
-        // A nice liberal timeout, most commands won't use anywhere near all of this except for the display refresh.
-        let timeout = Duration::from_secs(40);
+        let packed = pack_buffer(&img.index_img().into_vec(), img_res, BITS_PER_PIXEL)?;
+        self.send_command(Cmd::DataStartTransmission as u8, Some(&packed))?;
+
+        self.refresh(false)
+    }
+
+    /// Update a rectangular region of the display with the [`InkyImage`], whose top-left corner is placed at
+    /// the argument `(x, y)` origin. Only the packed bytes for the region are transmitted, and only the region
+    /// is refreshed, the rest of the display is left as-is.
+    ///
+    /// The region must lie within the display, and its horizontal origin and width must be multiples of the 4
+    /// pixels packed into each byte of display memory. As with [`Self::show`], the display must be initialized
+    /// again afterwards.
+    pub fn show_region(&mut self, img: &InkyImage, origin: (u32, u32)) -> InkyResult<()> {
+        use Jd79668Commands as Cmd;
+
+        debug!("attempting to show a new image region on display");
 
-        let packed = Self::pack_buffer(&img.index_img().into_vec())?;
+        if !self.initialized {
+            return Err(InkyError::Uninitialized);
+        }
+
+        let region = img.resolution();
+        validate_region(self.display_res, origin, region)?;
+
+        let packed = pack_buffer(&img.index_img().into_vec(), region, BITS_PER_PIXEL)?;
+
+        // Partial mode is left again by the refresh, once the region has been updated
+        self.send_command(Cmd::PartialIn as u8, None)?;
+        self.send_command(
+            Cmd::PartialWindow as u8,
+            Some(&partial_window(origin, region)),
+        )?;
         self.send_command(Cmd::DataStartTransmission as u8, Some(&packed))?;
 
-        // TODO (tff): this whole power on, display refresh, power off, deep sleep
-        // sequence is the 0x17 "Auto Sequence". maybe just use that?
+        self.refresh(true)
+    }
+
+    /// Refresh the display from the image data already transmitted, then power down and go into deep sleep,
+    /// either step-by-step or with a single Auto Sequence command if configured. A `partial` refresh leaves
+    /// partial mode between the refresh and powering down, so it is always sent step-by-step.
+    ///
+    /// The display has to be initialized again before it will accept more image data.
+    fn refresh(&mut self, partial: bool) -> InkyResult<()> {
+        use Jd79668Commands as Cmd;
+
+        if self.use_auto_sequence && !partial {
+            debug!("refreshing display using the auto sequence");
+
+            self.send_command(Cmd::DataStopTransmission as u8, None)?;
+            self.send_command_wait(
+                Cmd::AutoSequence as u8,
+                Some(&[AUTO_SEQUENCE_REFRESH_SLEEP]),
+                REFRESH_TIMEOUT,
+            )?;
+        } else {
+            self.send_command_wait(Cmd::PowerOn as u8, None, REFRESH_TIMEOUT)?;
+            self.send_command_wait(Cmd::DisplayRefresh as u8, Some(&[0x00]), REFRESH_TIMEOUT)?;
+
+            if partial {
+                self.send_command(Cmd::PartialOut as u8, None)?;
+            }
+
+            self.send_command_wait(Cmd::PowerOff as u8, Some(&[0x00]), REFRESH_TIMEOUT)?;
+            self.send_command_wait(Cmd::DeepSleep as u8, Some(&[0xa5]), REFRESH_TIMEOUT)?;
+        }
 
-        self.send_command_wait(Cmd::PowerOn as u8, None, timeout)?;
-        self.send_command_wait(Cmd::DisplayRefresh as u8, Some(&[0x00]), timeout)?;
-        self.send_command_wait(Cmd::PowerOff as u8, Some(&[0x00]), timeout)?;
-        self.send_command_wait(Cmd::DeepSleep as u8, Some(&[0xa5]), timeout)?;
+        // Deep sleep is only left by a hardware reset
+        self.initialized = false;
 
         Ok(())
     }
+}
 
-    // TODO (tff): unit test this
-    //
-    /// Pack a palletized image into a flattened 2-bit-per-pixel buffer to be sent via SPI to the display.
-    ///
-    /// Input: each pixel is a u8 ∈ {0,1,2,3}
-    ///
-    /// Output: Vec<u8> where each byte contains 4 pixels:
-    /// [p0 p1 p2 p3] → (p0<<6) | (p1<<4) | (p2<<2) | (p3)
-    ///
-    /// Returns an error if any pixel value > 3.
-    #[allow(clippy::get_first)]
-    fn pack_buffer(pixels: &[u8]) -> InkyResult<Vec<u8>> {
-        if !pixels.iter().all(|&p| p < 4) {
-            return Err(InkyError::InvalidPalettization {
-                index_min: 0,
-                index_max: 3,
-            });
+/// Check that a region of the argument size placed at the argument origin lies within a display of the argument
+/// resolution and is aligned to the pixel packing of display memory.
+fn validate_region(
+    display_res: Resolution,
+    origin: (u32, u32),
+    region: Resolution,
+) -> InkyResult<()> {
+    let (x, y) = origin;
+
+    let fits_x = x
+        .checked_add(region.width)
+        .is_some_and(|end| end <= display_res.width);
+    let fits_y = y
+        .checked_add(region.height)
+        .is_some_and(|end| end <= display_res.height);
+    if region.width == 0 || region.height == 0 || !fits_x || !fits_y {
+        return Err(InkyError::RegionOutOfBounds {
+            origin,
+            region,
+            display: display_res,
+        });
+    }
+
+    if !x.is_multiple_of(PIXELS_PER_BYTE) || !region.width.is_multiple_of(PIXELS_PER_BYTE) {
+        return Err(InkyError::UnalignedRegion {
+            x,
+            width: region.width,
+            alignment: PIXELS_PER_BYTE,
+        });
+    }
+
+    Ok(())
+}
+
+/// Data bytes for the [`Jd79668Commands::PartialWindow`] command: the horizontal start and (inclusive) end,
+/// then the vertical start and end, each as a big-endian u16, followed by a flag to scan only inside the
+/// window.
+fn partial_window(origin: (u32, u32), region: Resolution) -> [u8; 9] {
+    let (x, y) = origin;
+    let [x_start_h, x_start_l] = (x as u16).to_be_bytes();
+    let [x_end_h, x_end_l] = ((x + region.width - 1) as u16).to_be_bytes();
+    let [y_start_h, y_start_l] = (y as u16).to_be_bytes();
+    let [y_end_h, y_end_l] = ((y + region.height - 1) as u16).to_be_bytes();
+
+    [
+        x_start_h, x_start_l, x_end_h, x_end_l, y_start_h, y_start_l, y_end_h, y_end_l, 0x01,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use strum::IntoEnumIterator;
+
+    const DISPLAY: Resolution = Resolution {
+        width: 400,
+        height: 300,
+    };
+
+    #[test]
+    fn config_defaults_when_omitted() {
+        let cfg: Jd79668Config = toml::from_str(
+            r#"
+            spi_path = "/dev/spidev0.0"
+
+            [display_res]
+            width = 400
+            height = 300
+
+            [gpios]
+            gpio_chip = "/dev/gpiochip0"
+            chip_select = 8
+            data_cmd = 22
+            reset = 27
+            busy = 17
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(cfg.spi_max_speed_hz, 1_000_000);
+        assert_eq!(cfg.command_setup_delay, Duration::from_millis(300));
+        assert!(!cfg.use_auto_sequence);
+        assert_eq!(cfg.border, BorderColor::Black);
+    }
+
+    #[test]
+    fn config_setup_delay_round_trips_in_millis() {
+        let cfg = Jd79668Config {
+            command_setup_delay: Duration::from_millis(25),
+            ..Default::default()
+        };
+
+        let serialized = toml::to_string(&cfg).unwrap();
+        assert!(serialized.contains("command_setup_delay_ms = 25\n"));
+
+        let parsed: Jd79668Config = toml::from_str(&serialized).unwrap();
+        assert_eq!(parsed.command_setup_delay, Duration::from_millis(25));
+    }
+
+    #[test]
+    fn border_matches_palette_encoding() {
+        for color in InkyFourColorPalette::iter() {
+            let border = BorderColor::from(color);
+
+            assert_eq!(InkyFourColorPalette::from(border) as u8, color as u8);
+            assert_eq!(border.vcom_data_interval() >> 6, color as u8);
         }
 
-        let mut out = Vec::with_capacity(pixels.len().div_ceil(4));
+        assert_eq!(
+            BorderColor::default().vcom_data_interval(),
+            VCOM_DATA_INTERVAL
+        );
+        assert_eq!(BorderColor::Red.vcom_data_interval(), 0xF7);
+    }
+
+    #[test]
+    fn region_within_display() {
+        assert!(validate_region(DISPLAY, (0, 0), DISPLAY).is_ok());
+        assert!(validate_region(DISPLAY, (396, 299), Resolution::new(4, 1)).is_ok());
+    }
+
+    #[test]
+    fn region_out_of_bounds() {
+        let cases = [
+            ((4, 0), DISPLAY),
+            ((0, 1), DISPLAY),
+            ((400, 0), Resolution::new(4, 1)),
+            ((0, 300), Resolution::new(4, 1)),
+        ];
+
+        for (origin, region) in cases {
+            assert!(matches!(
+                validate_region(DISPLAY, origin, region),
+                Err(InkyError::RegionOutOfBounds { .. })
+            ));
+        }
+    }
 
-        for chunk in pixels.chunks(4) {
-            let p0 = *chunk.get(0).unwrap_or(&0);
-            let p1 = *chunk.get(1).unwrap_or(&0);
-            let p2 = *chunk.get(2).unwrap_or(&0);
-            let p3 = *chunk.get(3).unwrap_or(&0);
+    #[test]
+    fn region_end_overflows() {
+        let cases = [
+            ((u32::MAX - 3, 0), Resolution::new(8, 1)),
+            ((0, u32::MAX), Resolution::new(4, 2)),
+        ];
+
+        for (origin, region) in cases {
+            assert!(matches!(
+                validate_region(DISPLAY, origin, region),
+                Err(InkyError::RegionOutOfBounds { .. })
+            ));
+        }
+    }
 
-            let byte = (p0 << 6) | (p1 << 4) | (p2 << 2) | (p3);
+    #[test]
+    fn region_zero_size() {
+        for region in [Resolution::new(0, 10), Resolution::new(8, 0)] {
+            assert!(matches!(
+                validate_region(DISPLAY, (0, 0), region),
+                Err(InkyError::RegionOutOfBounds { .. })
+            ));
+        }
+    }
 
-            out.push(byte);
+    #[test]
+    fn region_unaligned() {
+        let cases = [
+            ((2, 0), Resolution::new(8, 1)),
+            ((4, 0), Resolution::new(6, 1)),
+        ];
+
+        for (origin, region) in cases {
+            assert!(matches!(
+                validate_region(DISPLAY, origin, region),
+                Err(InkyError::UnalignedRegion { alignment: 4, .. })
+            ));
         }
+    }
+
+    #[test]
+    fn partial_window_bytes() {
+        // x 252..=299, y 250..=299 crosses into the high byte in both directions, within the display
+        let (origin, region) = ((252, 250), Resolution::new(48, 50));
+        assert!(validate_region(DISPLAY, origin, region).is_ok());
 
-        Ok(out)
+        assert_eq!(
+            partial_window(origin, region),
+            [0x00, 0xFC, 0x01, 0x2B, 0x00, 0xFA, 0x01, 0x2B, 0x01]
+        );
     }
 }
@@ -77,7 +77,7 @@ impl ProbePeripherals {
 /// Return a list of filepaths to devices in the argument directory with the argument filename prefix.
 ///
 /// e.g. list_matching("/dev", "spidev") -> [ /dev/spidev0.0 ]
-fn list_matching(dir: Utf8PathBuf, prefix: &str) -> Vec<Utf8PathBuf> {
+pub(crate) fn list_matching(dir: Utf8PathBuf, prefix: &str) -> Vec<Utf8PathBuf> {
     let mut entries = Vec::new();
 
     if let Ok(read_dir) = dir.read_dir_utf8() {
@@ -0,0 +1,251 @@
+//! Rasterization of simple text labels using the bundled 8x8 bitmap font from [`font8x8`].
+
+use crate::{InkyError, InkyResult, Resolution};
+use font8x8::{BASIC_FONTS, LATIN_FONTS, UnicodeFonts};
+use image::{Rgb, RgbImage};
+
+/// Width and height in pixels of each glyph in the bitmap font, before scaling.
+const GLYPH_SIZE: u32 = 8;
+
+/// Glyph rendered in place of characters the bitmap font doesn't cover, it covers ASCII and Latin-1.
+const REPLACEMENT_CHAR: char = '?';
+
+/// Horizontal alignment of each line of text.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// Options for rendering text with [`crate::ImagePreProcessor::new_text`].
+#[derive(Debug, Copy, Clone)]
+pub struct TextOptions {
+    /// Color of the text, must belong to the color palette
+    pub foreground: Rgb<u8>,
+
+    /// Color of the rest of the image, must belong to the color palette
+    pub background: Rgb<u8>,
+
+    /// Integer scale factor for the 8x8 pixel font, e.g. a scale of 3 renders glyphs 24 pixels tall. Must be
+    /// at least 1.
+    pub scale: u32,
+
+    /// Horizontal alignment of each line of text
+    pub align: TextAlign,
+}
+
+impl Default for TextOptions {
+    fn default() -> Self {
+        Self {
+            foreground: Rgb([0, 0, 0]),
+            background: Rgb([255, 255, 255]),
+            scale: 2,
+            align: TextAlign::Left,
+        }
+    }
+}
+
+/// Render the argument text onto a background-filled image of the argument resolution. Lines are broken at
+/// newlines and word-wrapped at the right edge, any lines which don't fit vertically are clipped. The scale
+/// must be nonzero and leave room for at least a single glyph.
+pub(crate) fn render_text(text: &str, opts: &TextOptions, res: Resolution) -> InkyResult<RgbImage> {
+    let cell = GLYPH_SIZE
+        .checked_mul(opts.scale)
+        .filter(|&cell| cell > 0 && cell <= res.width && cell <= res.height)
+        .ok_or(InkyError::InvalidTextScale {
+            scale: opts.scale,
+            res,
+        })?;
+
+    let mut canvas = RgbImage::from_pixel(res.width, res.height, opts.background);
+    let cols = (res.width / cell) as usize;
+    let rows = (res.height / cell) as usize;
+
+    for (row, line) in wrap(text, cols).iter().take(rows).enumerate() {
+        let line_width = line.chars().count() as u32 * cell;
+        let x0 = match opts.align {
+            TextAlign::Left => 0,
+            TextAlign::Center => (res.width - line_width) / 2,
+            TextAlign::Right => res.width - line_width,
+        };
+        let y0 = row as u32 * cell;
+
+        for (col, c) in line.chars().enumerate() {
+            let glyph = BASIC_FONTS
+                .get(c)
+                .or_else(|| LATIN_FONTS.get(c))
+                .or_else(|| BASIC_FONTS.get(REPLACEMENT_CHAR))
+                .expect("the replacement character is in the basic font");
+
+            draw_glyph(
+                &mut canvas,
+                glyph,
+                x0 + col as u32 * cell,
+                y0,
+                cell,
+                opts.foreground,
+            );
+        }
+    }
+
+    Ok(canvas)
+}
+
+/// Draw a single glyph into a `cell` pixel square with its top-left corner at the argument coordinates.
+fn draw_glyph(canvas: &mut RgbImage, glyph: [u8; 8], x0: u32, y0: u32, cell: u32, color: Rgb<u8>) {
+    let scale = cell / GLYPH_SIZE;
+
+    for y in 0..cell {
+        // Each byte is a row of the glyph, with the least significant bit being the leftmost pixel
+        let row = glyph[(y / scale) as usize];
+
+        for x in 0..cell {
+            if row & (1 << (x / scale)) != 0 {
+                canvas.put_pixel(x0 + x, y0 + y, color);
+            }
+        }
+    }
+}
+
+/// Break text into lines of at most `cols` characters, at newlines and then greedily between words. Words
+/// longer than a whole line are split across lines.
+fn wrap(text: &str, cols: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    if cols == 0 {
+        return lines;
+    }
+
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        let mut line_len = 0;
+
+        for word in paragraph.split_whitespace() {
+            let chars: Vec<char> = word.chars().collect();
+
+            for piece in chars.chunks(cols) {
+                if line_len == 0 {
+                    line_len = piece.len();
+                    line.extend(piece);
+                } else if line_len + 1 + piece.len() <= cols {
+                    line_len += 1 + piece.len();
+                    line.push(' ');
+                    line.extend(piece);
+                } else {
+                    lines.push(std::mem::take(&mut line));
+                    line_len = piece.len();
+                    line.extend(piece);
+                }
+            }
+        }
+
+        lines.push(line);
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrap_between_words() {
+        assert_eq!(
+            wrap("the quick brown fox", 10),
+            vec!["the quick", "brown fox"]
+        );
+    }
+
+    #[test]
+    fn wrap_keeps_newlines() {
+        assert_eq!(wrap("one\n\ntwo", 10), vec!["one", "", "two"]);
+    }
+
+    #[test]
+    fn wrap_splits_long_words() {
+        assert_eq!(wrap("a abcdefgh", 4), vec!["a", "abcd", "efgh"]);
+    }
+
+    #[test]
+    fn wrap_no_columns() {
+        assert!(wrap("anything", 0).is_empty());
+    }
+
+    #[test]
+    fn render_invalid_scale() {
+        let res = Resolution::new(400, 300);
+
+        for scale in [0, 38, u32::MAX / 4] {
+            let opts = TextOptions {
+                scale,
+                ..Default::default()
+            };
+
+            assert!(matches!(
+                render_text("hi", &opts, res),
+                Err(InkyError::InvalidTextScale { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn render_largest_scale() {
+        let opts = TextOptions {
+            scale: 37,
+            ..Default::default()
+        };
+
+        // A single 296 pixel glyph fits vertically on a 300 pixel tall display, each glyph pixel is 37 pixels
+        // square and the top row of an "I" starts one glyph pixel in
+        let canvas = render_text("I", &opts, Resolution::new(400, 300)).unwrap();
+        assert_eq!(*canvas.get_pixel(36, 0), opts.background);
+        assert_eq!(*canvas.get_pixel(37, 0), opts.foreground);
+        assert_eq!(*canvas.get_pixel(184, 36), opts.foreground);
+        assert_eq!(*canvas.get_pixel(185, 36), opts.background);
+        assert_eq!(*canvas.get_pixel(295, 295), opts.background);
+    }
+
+    /// The rendered image as rows of `#` for foreground pixels and `.` for everything else.
+    fn ascii_art(canvas: &RgbImage, foreground: Rgb<u8>) -> Vec<String> {
+        canvas
+            .rows()
+            .map(|row| {
+                row.map(|&p| if p == foreground { '#' } else { '.' })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// The "L" glyph from the basic font, its foot points right so a mirrored decoding wouldn't match.
+    const L_GLYPH: [&str; 8] = [
+        "####....", ".##.....", ".##.....", ".##.....", ".##...#.", ".##..##.", "#######.",
+        "........",
+    ];
+
+    #[test]
+    fn render_aligns_glyph_pixels() {
+        let cases = [
+            (TextAlign::Left, "", "...."),
+            (TextAlign::Center, "..", ".."),
+            (TextAlign::Right, "....", ""),
+        ];
+
+        for (align, before, after) in cases {
+            let opts = TextOptions {
+                scale: 1,
+                align,
+                ..Default::default()
+            };
+            let canvas = render_text("L", &opts, Resolution::new(12, 8)).unwrap();
+
+            let expected: Vec<String> = L_GLYPH
+                .iter()
+                .map(|row| format!("{before}{row}{after}"))
+                .collect();
+            assert_eq!(ascii_art(&canvas, opts.foreground), expected, "{align:?}");
+        }
+    }
+}
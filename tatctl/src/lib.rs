@@ -1,5 +1,8 @@
 use clap::ValueEnum;
-use libtatted::{InkyFourColorMap, InkyFourColorPalette, MonoColorMap, SupportedColorMaps};
+use libtatted::{
+    BayerMatrix, BorderColor, DitherMode, FitStrategy, InkyFourColorMap, InkyFourColorPalette,
+    MonoColorMap, Rotation, SupportedColorMaps, TextAlign,
+};
 use strum::Display;
 
 /// Colors supported by the JD79668, a mirror of [`libtatted::InkyFourColorPalette`]` for use with clap.
@@ -23,6 +26,12 @@ impl From<CliColors> for InkyFourColorPalette {
     }
 }
 
+impl From<CliColors> for BorderColor {
+    fn from(value: CliColors) -> Self {
+        InkyFourColorPalette::from(value).into()
+    }
+}
+
 /// Supported color maps for spatial quantization of images
 #[derive(Debug, Copy, Clone, ValueEnum, Display)]
 #[strum(serialize_all = "kebab-case")]
@@ -39,3 +48,135 @@ impl From<CliColorMaps> for SupportedColorMaps {
         }
     }
 }
+
+/// Strategies for fitting images to the display resolution, a mirror of [`libtatted::FitStrategy`] for use with clap.
+#[derive(Debug, Copy, Clone, ValueEnum, Display)]
+#[strum(serialize_all = "kebab-case")]
+pub enum CliFitStrategy {
+    /// Require the image to already be the display resolution
+    Exact,
+
+    /// Scale to fit within the display, letterboxing with white
+    Contain,
+
+    /// Scale to cover the display, center-cropping the overhang
+    Cover,
+
+    /// Resize to the display resolution, ignoring aspect ratio
+    Stretch,
+}
+
+impl From<CliFitStrategy> for FitStrategy {
+    fn from(value: CliFitStrategy) -> Self {
+        match value {
+            CliFitStrategy::Exact => FitStrategy::Exact,
+            CliFitStrategy::Contain => FitStrategy::Contain,
+            CliFitStrategy::Cover => FitStrategy::Cover,
+            CliFitStrategy::Stretch => FitStrategy::Stretch,
+        }
+    }
+}
+
+/// Clockwise image rotations in degrees, a mirror of [`libtatted::Rotation`] for use with clap.
+#[derive(Debug, Copy, Clone, ValueEnum, Display)]
+pub enum CliRotation {
+    #[value(name = "0")]
+    #[strum(serialize = "0")]
+    R0,
+
+    #[value(name = "90")]
+    #[strum(serialize = "90")]
+    R90,
+
+    #[value(name = "180")]
+    #[strum(serialize = "180")]
+    R180,
+
+    #[value(name = "270")]
+    #[strum(serialize = "270")]
+    R270,
+}
+
+impl From<CliRotation> for Rotation {
+    fn from(value: CliRotation) -> Self {
+        match value {
+            CliRotation::R0 => Rotation::R0,
+            CliRotation::R90 => Rotation::R90,
+            CliRotation::R180 => Rotation::R180,
+            CliRotation::R270 => Rotation::R270,
+        }
+    }
+}
+
+/// Dithering modes for the preprocessing pipeline, a mirror of [`libtatted::DitherMode`] for use with clap.
+#[derive(Debug, Copy, Clone, ValueEnum, Display)]
+#[strum(serialize_all = "kebab-case")]
+pub enum CliDitherMode {
+    /// Simple color quantization
+    None,
+
+    /// Floyd-Steinberg error diffusion
+    FloydSteinberg,
+
+    /// Ordered dithering with a Bayer matrix, see --bayer-size
+    Ordered,
+}
+
+/// Bayer matrix sizes for ordered dithering, a mirror of [`libtatted::BayerMatrix`] for use with clap.
+#[derive(Debug, Copy, Clone, ValueEnum, Display)]
+pub enum CliBayerSize {
+    #[value(name = "2")]
+    #[strum(serialize = "2")]
+    Bayer2x2,
+
+    #[value(name = "4")]
+    #[strum(serialize = "4")]
+    Bayer4x4,
+
+    #[value(name = "8")]
+    #[strum(serialize = "8")]
+    Bayer8x8,
+}
+
+impl From<CliBayerSize> for BayerMatrix {
+    fn from(value: CliBayerSize) -> Self {
+        match value {
+            CliBayerSize::Bayer2x2 => BayerMatrix::Bayer2x2,
+            CliBayerSize::Bayer4x4 => BayerMatrix::Bayer4x4,
+            CliBayerSize::Bayer8x8 => BayerMatrix::Bayer8x8,
+        }
+    }
+}
+
+/// Resolve the dithering CLI arguments into a [`DitherMode`], the legacy `--dither` flag is an alias for
+/// Floyd-Steinberg.
+pub fn dither_mode(dither: bool, mode: CliDitherMode, bayer_size: CliBayerSize) -> DitherMode {
+    if dither {
+        return DitherMode::FloydSteinberg;
+    }
+
+    match mode {
+        CliDitherMode::None => DitherMode::None,
+        CliDitherMode::FloydSteinberg => DitherMode::FloydSteinberg,
+        CliDitherMode::Ordered => DitherMode::Ordered(bayer_size.into()),
+    }
+}
+
+/// Horizontal text alignment, a mirror of [`libtatted::TextAlign`] for use with clap.
+#[derive(Debug, Copy, Clone, ValueEnum, Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum CliTextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+impl From<CliTextAlign> for TextAlign {
+    fn from(value: CliTextAlign) -> Self {
+        match value {
+            CliTextAlign::Left => TextAlign::Left,
+            CliTextAlign::Center => TextAlign::Center,
+            CliTextAlign::Right => TextAlign::Right,
+        }
+    }
+}
@@ -1,10 +1,16 @@
-use camino::Utf8PathBuf;
+use anyhow::Context;
+use camino::{Utf8Path, Utf8PathBuf};
 use clap::{Parser, Subcommand};
 use libtatted::{
-    ImagePreProcessor, InkyFourColorMap, InkyFourColorPalette, InkyJd79668, Jd79668Config,
-    MonoColorMap, ProbePeripherals, Resolution, Rgb, SupportedColorMaps,
+    DisplaySpec, EepromParseError, I2cBusReports, I2cProbeStatus, ImagePreProcessor,
+    InkyFourColorMap, InkyFourColorPalette, InkyImage, InkyJd79668, Jd79668Config, MonoColorMap,
+    ProbePeripherals, Rgb, SupportedColorMaps, TextOptions, parse_eeprom, read_eeprom_raw,
+};
+use log::warn;
+use tatctl::{
+    CliBayerSize, CliColorMaps, CliColors, CliDitherMode, CliFitStrategy, CliRotation,
+    CliTextAlign, dither_mode,
 };
-use tatctl::{CliColorMaps, CliColors};
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
@@ -15,6 +21,10 @@ struct Cli {
     /// Turn debugging information on
     #[arg(short, long)]
     debug: bool,
+
+    /// TOML file to load the display configuration from, the Raspberry Pi wHAT defaults are used otherwise
+    #[arg(long, global = true)]
+    config: Option<Utf8PathBuf>,
 }
 
 #[derive(Subcommand)]
@@ -26,6 +36,23 @@ enum Commands {
     Display {
         #[command(subcommand)]
         command: DisplayCommands,
+
+        /// Read the display resolution from the EEPROM on this I2C bus rather than taking it from the display
+        /// configuration, `detect` reads only this bus
+        #[arg(long)]
+        i2c_path: Option<Utf8PathBuf>,
+    },
+
+    /// Display configuration management
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+
+    /// Display EEPROM inspection
+    Eeprom {
+        #[command(subcommand)]
+        command: EepromCommands,
     },
 
     /// Image pre-processing steps for e-ink rendering
@@ -42,10 +69,26 @@ enum Commands {
         #[arg(short, long, default_value_t = CliColorMaps::InkyFourColor)]
         colormap: CliColorMaps,
 
-        /// Enable Floyd-Steinberg dithering in the preprocessing pipeline, simple color quantization
-        /// is the default
-        #[arg(short, long)]
+        /// Enable Floyd-Steinberg dithering in the preprocessing pipeline, an alias for
+        /// `--dither-mode floyd-steinberg`
+        #[arg(short, long, conflicts_with = "dither_mode")]
         dither: bool,
+
+        /// Dithering mode for the preprocessing pipeline, simple color quantization is the default
+        #[arg(long, default_value_t = CliDitherMode::None)]
+        dither_mode: CliDitherMode,
+
+        /// Size of the Bayer matrix used for ordered dithering
+        #[arg(long, default_value_t = CliBayerSize::Bayer4x4)]
+        bayer_size: CliBayerSize,
+
+        /// How to fit images which don't match the display resolution
+        #[arg(short, long, default_value_t = CliFitStrategy::Exact)]
+        fit: CliFitStrategy,
+
+        /// Rotate the image clockwise by this many degrees before fitting it to the display
+        #[arg(short, long, default_value_t = CliRotation::R0)]
+        rotate: CliRotation,
     },
 }
 
@@ -53,6 +96,9 @@ enum Commands {
 /// Subcommands for display manipulation
 #[derive(Clone, Debug, Subcommand)]
 pub enum DisplayCommands {
+    /// Read the EEPROM on each I2C bus, or only the one given by `--i2c-path`, to identify the connected display
+    Detect,
+
     /// Perform a hardware reset on the display
     Reset,
 
@@ -65,10 +111,34 @@ pub enum DisplayCommands {
         #[arg(short, long)]
         image_path: Utf8PathBuf,
 
-        /// Enable Floyd-Steinberg dithering in the preprocessing pipeline, simple color quantization
-        /// is the default
-        #[arg(short, long)]
+        /// Enable Floyd-Steinberg dithering in the preprocessing pipeline, an alias for
+        /// `--dither-mode floyd-steinberg`
+        #[arg(short, long, conflicts_with = "dither_mode")]
         dither: bool,
+
+        /// Dithering mode for the preprocessing pipeline, simple color quantization is the default
+        #[arg(long, default_value_t = CliDitherMode::None)]
+        dither_mode: CliDitherMode,
+
+        /// Size of the Bayer matrix used for ordered dithering
+        #[arg(long, default_value_t = CliBayerSize::Bayer4x4)]
+        bayer_size: CliBayerSize,
+
+        /// How to fit images which don't match the display resolution
+        #[arg(short, long, default_value_t = CliFitStrategy::Exact)]
+        fit: CliFitStrategy,
+
+        /// Rotate the image clockwise by this many degrees before fitting it to the display
+        #[arg(short, long, default_value_t = CliRotation::R0)]
+        rotate: CliRotation,
+
+        /// Save the pre-processed image to this path before showing it on the display
+        #[arg(short, long)]
+        save: Option<Utf8PathBuf>,
+
+        /// Color of the border around the active area of the display, overriding the configured border
+        #[arg(long)]
+        border: Option<CliColors>,
     },
 
     /// Render a solid color
@@ -76,9 +146,122 @@ pub enum DisplayCommands {
         /// Which solid color to render
         #[arg(short, long, default_value_t = CliColors::Red)]
         color: CliColors,
+
+        /// Save the pre-processed image to this path before showing it on the display
+        #[arg(short, long)]
+        save: Option<Utf8PathBuf>,
+
+        /// Color of the border around the active area of the display, overriding the configured border
+        #[arg(long)]
+        border: Option<CliColors>,
+    },
+
+    /// Render text
+    RenderText {
+        /// The text to render, long lines are wrapped at the edge of the display
+        #[arg(short, long)]
+        text: String,
+
+        /// Color of the text
+        #[arg(short, long, default_value_t = CliColors::Black)]
+        color: CliColors,
+
+        /// Color of the background
+        #[arg(short, long, default_value_t = CliColors::White)]
+        background: CliColors,
+
+        /// Scale factor for the 8x8 pixel font
+        #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u32).range(1..))]
+        scale: u32,
+
+        /// Horizontal alignment of each line of text
+        #[arg(short, long, default_value_t = CliTextAlign::Left)]
+        align: CliTextAlign,
+
+        /// Save the pre-processed image to this path before showing it on the display
+        #[arg(short, long)]
+        save: Option<Utf8PathBuf>,
+
+        /// Color of the border around the active area of the display, overriding the configured border
+        #[arg(long)]
+        border: Option<CliColors>,
+    },
+}
+
+/// Subcommands for display configuration
+#[derive(Clone, Debug, Subcommand)]
+pub enum ConfigCommands {
+    /// Print the current display configuration as TOML, a starting point for a custom config file
+    Dump,
+}
+
+/// Subcommands for display EEPROM inspection
+#[derive(Clone, Debug, Subcommand)]
+pub enum EepromCommands {
+    /// Read the display EEPROM and print its parsed contents
+    Dump {
+        /// The I2C bus the display's EEPROM is on
+        #[arg(short, long, default_value_t = Utf8PathBuf::from("/dev/i2c-1"))]
+        i2c_path: Utf8PathBuf,
+
+        /// Print the raw bytes in hex rather than parsing them
+        #[arg(long)]
+        hex: bool,
     },
 }
 
+/// Load the display configuration from the TOML file at the argument path, or the default configuration if
+/// no path is provided.
+fn load_config(path: Option<&Utf8Path>) -> anyhow::Result<Jd79668Config> {
+    let Some(path) = path else {
+        return Ok(Jd79668Config::default());
+    };
+
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read config file '{path}'"))?;
+
+    toml::from_str(&contents).with_context(|| format!("failed to parse config file '{path}'"))
+}
+
+/// Connect to the display, taking its resolution from the EEPROM on the argument I2C bus if one is provided,
+/// and initialize it ready for a new image.
+fn connect(config: Jd79668Config, i2c_path: Option<Utf8PathBuf>) -> anyhow::Result<InkyJd79668> {
+    println!("Connecting to Inky display...");
+    let mut inky = match i2c_path {
+        Some(path) => InkyJd79668::from_eeprom(config, path)?,
+        None => InkyJd79668::new(config)?,
+    };
+
+    println!("Initializing Inky display...");
+    inky.initialize()?;
+
+    Ok(inky)
+}
+
+/// Save the prepared image if a path is provided and override the configured border color if one is provided,
+/// then show the image on the display. `what` names the image in the progress message, e.g. "text".
+fn show_image(
+    inky: &mut InkyJd79668,
+    inky_img: &InkyImage,
+    what: &str,
+    save: Option<Utf8PathBuf>,
+    border: Option<CliColors>,
+) -> anyhow::Result<()> {
+    if let Some(path) = save {
+        println!("Writing image to '{path}'");
+        inky_img.save(path)?;
+    }
+
+    if let Some(color) = border {
+        inky.set_border(color.into())?;
+    }
+
+    println!("Showing {what}...");
+    inky.show(inky_img)?;
+
+    Ok(())
+}
+
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
@@ -90,7 +273,7 @@ fn main() -> anyhow::Result<()> {
     // Log messages below the provided level will be filtered out, the RUST_LOG env var is not used here.
     simple_logger::init_with_level(level)?;
 
-    let res = Resolution::new(400, 300);
+    let config_path = cli.config.as_deref();
 
     match cli.command {
         Commands::Probe => {
@@ -98,20 +281,72 @@ fn main() -> anyhow::Result<()> {
             let probe = ProbePeripherals::probe();
             println!("{}", probe);
         }
+        Commands::Config {
+            command: ConfigCommands::Dump,
+        } => {
+            let config = load_config(config_path)?;
+
+            // Print nothing but the config so the output can be redirected straight to a file
+            print!("{}", toml::to_string_pretty(&config)?);
+            return Ok(());
+        }
+        Commands::Eeprom {
+            command: EepromCommands::Dump { i2c_path, hex },
+        } => {
+            let buf = read_eeprom_raw(&i2c_path)
+                .with_context(|| format!("failed to read EEPROM on '{i2c_path}'"))?;
+
+            if hex {
+                for (i, line) in buf.chunks(16).enumerate() {
+                    let bytes: Vec<String> = line.iter().map(|b| format!("{b:02x}")).collect();
+                    println!("{:04x}: {}", i * 16, bytes.join(" "));
+                }
+            } else {
+                match parse_eeprom(&buf) {
+                    Ok(info) => {
+                        println!("width: {}", info.width);
+                        println!("height: {}", info.height);
+                        println!("color: {}", info.color);
+                        println!("pcb variant: {}", info.pcb_variant);
+                        println!(
+                            "display variant: {} ({})",
+                            info.display_variant,
+                            info.variant_name().unwrap_or("unknown")
+                        );
+                        println!("written: {}", info.write_time.as_deref().unwrap_or("-"));
+                    }
+                    Err(EepromParseError::Blank) => println!("EEPROM is blank"),
+                    Err(EepromParseError::Invalid) => {
+                        println!("EEPROM contents are invalid, use --hex to inspect the raw bytes")
+                    }
+                }
+            }
+        }
         Commands::Image {
             image_path,
             out_path,
             colormap,
             dither,
+            dither_mode: mode,
+            bayer_size,
+            fit,
+            rotate,
         } => {
+            let res = load_config(config_path)?.display_res;
+            let dither = dither_mode(dither, mode, bayer_size);
+
             println!("Processing image...");
             let inky_img = match SupportedColorMaps::from(colormap) {
                 SupportedColorMaps::InkyFourColor(InkyFourColorMap) => {
-                    let preproc = ImagePreProcessor::new(InkyFourColorMap, res);
+                    let preproc = ImagePreProcessor::new(InkyFourColorMap, res)
+                        .with_fit(fit.into())
+                        .with_rotation(rotate.into());
                     preproc.prepare_from_path(image_path, dither)?
                 }
                 SupportedColorMaps::Mono(MonoColorMap) => {
-                    let preproc = ImagePreProcessor::new(MonoColorMap, res);
+                    let preproc = ImagePreProcessor::new(MonoColorMap, res)
+                        .with_fit(fit.into())
+                        .with_rotation(rotate.into());
                     preproc.prepare_from_path(image_path, dither)?
                 }
             };
@@ -119,43 +354,100 @@ fn main() -> anyhow::Result<()> {
             println!("Writing image to '{out_path}'");
             inky_img.save(out_path)?;
         }
-        Commands::Display { command } => {
-            println!("Connecting to Inky display...");
-            let mut inky = InkyJd79668::new(Jd79668Config::default())?;
-
-            println!("Initializing Inky display...");
-            inky.initialize()?;
+        Commands::Display { command, i2c_path } => {
+            let config = load_config(config_path)?;
 
-            // Would like to add the option to save the preprocessed image to the filesystem here before
-            // showing it on the display.
             match command {
+                DisplayCommands::Detect => {
+                    println!("Reading display EEPROMs...");
+                    let reports = match i2c_path {
+                        Some(path) => I2cBusReports::probe_paths([path]),
+                        None => I2cBusReports::probe(),
+                    };
+                    println!("{}", reports);
+
+                    let expected = config.display_res;
+                    for report in reports.iter() {
+                        if let I2cProbeStatus::Detected(info) = &report.status
+                            && info.display_spec() == Some(DisplaySpec::Jd79668)
+                            && info.resolution() != expected
+                        {
+                            warn!(
+                                "JD79668 on '{}' reports a {}x{} panel but the driver is configured for {}x{}, \
+                                 images will not render correctly",
+                                report.path,
+                                info.width,
+                                info.height,
+                                expected.width,
+                                expected.height
+                            );
+                        }
+                    }
+                }
                 DisplayCommands::Reset => {
+                    let mut inky = connect(config, i2c_path)?;
+
                     println!("Performing hardware reset...");
                     inky.hardware_reset()?;
                 }
 
                 DisplayCommands::Clear => {
-                    let preproc = ImagePreProcessor::new(InkyFourColorMap, res);
+                    let mut inky = connect(config, i2c_path)?;
+                    let preproc = ImagePreProcessor::new(InkyFourColorMap, inky.display_res());
                     let inky_img =
                         preproc.new_color(libtatted::Rgb::from(InkyFourColorPalette::White))?;
 
                     println!("Clearing display...");
                     inky.show(&inky_img)?;
                 }
-                DisplayCommands::RenderImage { image_path, dither } => {
-                    let preproc = ImagePreProcessor::new(InkyFourColorMap, res);
+                DisplayCommands::RenderImage {
+                    image_path,
+                    dither,
+                    dither_mode: mode,
+                    bayer_size,
+                    fit,
+                    rotate,
+                    save,
+                    border,
+                } => {
+                    let mut inky = connect(config, i2c_path)?;
+                    let dither = dither_mode(dither, mode, bayer_size);
+                    let preproc = ImagePreProcessor::new(InkyFourColorMap, inky.display_res())
+                        .with_fit(fit.into())
+                        .with_rotation(rotate.into());
                     let inky_img = preproc.prepare_from_path(image_path, dither)?;
-
-                    println!("Showing image...");
-                    inky.show(&inky_img)?;
+                    show_image(&mut inky, &inky_img, "image", save, border)?;
                 }
-                DisplayCommands::RenderColor { color } => {
+                DisplayCommands::RenderColor {
+                    color,
+                    save,
+                    border,
+                } => {
+                    let mut inky = connect(config, i2c_path)?;
                     let palette_color = InkyFourColorPalette::from(color);
-                    let preproc = ImagePreProcessor::new(InkyFourColorMap, res);
+                    let preproc = ImagePreProcessor::new(InkyFourColorMap, inky.display_res());
                     let inky_img = preproc.new_color(Rgb::from(palette_color))?;
-
-                    println!("Showing color...");
-                    inky.show(&inky_img)?;
+                    show_image(&mut inky, &inky_img, "color", save, border)?;
+                }
+                DisplayCommands::RenderText {
+                    text,
+                    color,
+                    background,
+                    scale,
+                    align,
+                    save,
+                    border,
+                } => {
+                    let mut inky = connect(config, i2c_path)?;
+                    let preproc = ImagePreProcessor::new(InkyFourColorMap, inky.display_res());
+                    let opts = TextOptions {
+                        foreground: Rgb::from(InkyFourColorPalette::from(color)),
+                        background: Rgb::from(InkyFourColorPalette::from(background)),
+                        scale,
+                        align: align.into(),
+                    };
+                    let inky_img = preproc.new_text(&text, opts)?;
+                    show_image(&mut inky, &inky_img, "text", save, border)?;
                 }
             }
         }